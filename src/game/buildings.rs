@@ -0,0 +1,347 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::game::{
+    card::{kill_card, Card, CardClass, CardDied, CardType, StackRoots},
+    combat::CombatHit,
+    label::{hud_text, set_text, set_text_color},
+    rng::GameRng,
+    targeting::SpatialGrid,
+};
+
+pub struct BuildingPlugin;
+
+impl Plugin for BuildingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Housing>()
+            .add_systems(
+                PostUpdate,
+                on_spawn_building.after(crate::game::card::on_spawn_card),
+            )
+            .add_systems(Startup, spawn_population_counter)
+            .add_systems(
+                Update,
+                count_population.before(crate::game::card::evaluate_stacks),
+            )
+            .add_systems(Update, show_population.after(count_population))
+            .add_systems(
+                Update,
+                man_watchtowers
+                    .after(crate::game::targeting::index_cards)
+                    .after(crate::game::card::combat),
+            );
+    }
+}
+
+/// A building a villager puts up from the resources stacked on it.
+pub struct Recipe {
+    pub result: CardType,
+    pub ingredients: &'static [(CardType, usize)],
+    /// Seconds it takes to build.
+    pub build_time: f32,
+}
+
+impl Recipe {
+    pub const ALL: [Recipe; 3] = [
+        Recipe {
+            result: CardType::House,
+            ingredients: &[(CardType::Log, 3)],
+            build_time: 10.0,
+        },
+        Recipe {
+            result: CardType::Storage,
+            ingredients: &[(CardType::Log, 5)],
+            build_time: 12.0,
+        },
+        Recipe {
+            result: CardType::Watchtower,
+            ingredients: &[(CardType::Log, 4), (CardType::Coin, 2)],
+            build_time: 15.0,
+        },
+    ];
+
+    /// The recipe built by a stack holding exactly one villager and the recipe's ingredients.
+    pub fn matching(card_types: &HashMap<CardType, usize>) -> Option<&'static Recipe> {
+        if card_types.get(&CardType::Villager) != Some(&1) {
+            return None;
+        }
+        Self::ALL.iter().find(|recipe| {
+            card_types.len() == recipe.ingredients.len() + 1
+                && recipe
+                    .ingredients
+                    .iter()
+                    .all(|(card_type, count)| card_types.get(card_type) == Some(count))
+        })
+    }
+}
+
+/// How many villagers the village has, and how many it has room for. Villagers only breed while
+/// there is room left.
+#[derive(Resource)]
+pub struct Housing {
+    pub population: usize,
+    pub capacity: usize,
+}
+
+impl Default for Housing {
+    fn default() -> Self {
+        Self {
+            population: 0,
+            capacity: Self::BASE_CAPACITY,
+        }
+    }
+}
+
+impl Housing {
+    /// Room the village has without any houses.
+    pub const BASE_CAPACITY: usize = 4;
+    pub const PER_HOUSE: usize = 2;
+}
+
+/// Shoots the nearest enemy in range whenever it has reloaded.
+#[derive(Component)]
+pub struct Watchtower {
+    pub reload: Timer,
+}
+
+impl Watchtower {
+    pub const RANGE: f32 = 3.5;
+}
+
+/// Keeps thieves away from the resources lying around it.
+#[derive(Component)]
+pub struct Storage;
+
+impl Storage {
+    pub const GUARD_RADIUS: f32 = 2.0;
+}
+
+fn on_spawn_building(mut commands: Commands, cards: Query<(Entity, &Card), Added<Card>>) {
+    for (entity, card) in &cards {
+        match card.card_type() {
+            CardType::Watchtower => {
+                let reload = card.info.stats.attack_cooldown();
+                commands.entity(entity).insert(Watchtower {
+                    reload: Timer::from_seconds(reload, TimerMode::Once),
+                });
+            }
+            CardType::Storage => {
+                commands.entity(entity).insert(Storage);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn count_population(mut housing: ResMut<Housing>, cards: Query<&Card>) {
+    let mut population = 0;
+    let mut houses = 0;
+    for card in &cards {
+        match card.card_type() {
+            CardType::Villager => population += 1,
+            CardType::House => houses += 1,
+            _ => {}
+        }
+    }
+    let capacity = Housing::BASE_CAPACITY + houses * Housing::PER_HOUSE;
+    if housing.population != population || housing.capacity != capacity {
+        housing.population = population;
+        housing.capacity = capacity;
+    }
+}
+
+#[derive(Component)]
+pub struct PopulationCounter;
+
+fn spawn_population_counter(mut commands: Commands) {
+    commands
+        .spawn(hud_text(
+            18.0,
+            Color::WHITE,
+            Style {
+                right: Val::Px(10.0),
+                // below the clock
+                top: Val::Px(34.0),
+                ..default()
+            },
+        ))
+        .insert(PopulationCounter);
+}
+
+fn show_population(housing: Res<Housing>, mut counters: Query<&mut Text, With<PopulationCounter>>) {
+    let value = format!("Population {}/{}", housing.population, housing.capacity);
+    let color = if housing.population >= housing.capacity {
+        Color::rgb(1.0, 0.7, 0.4)
+    } else {
+        Color::WHITE
+    };
+    for mut text in &mut counters {
+        set_text(&mut text, &value);
+        set_text_color(&mut text, color);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn man_watchtowers(
+    mut commands: Commands,
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    mut rng: ResMut<GameRng>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut hits: EventWriter<CombatHit>,
+    mut deaths: EventWriter<CardDied>,
+    mut towers: Query<(Entity, &mut Watchtower, &Transform)>,
+    mut cards: Query<&mut Card>,
+) {
+    for (entity, mut tower, transform) in &mut towers {
+        // a loaded tower waits for something to come in range
+        if !tower.reload.tick(time.delta()).finished() {
+            continue;
+        }
+        let position = transform.translation;
+        let mut targets: Vec<_> = grid
+            .within(position, Watchtower::RANGE)
+            .filter(|s| s.class == CardClass::Enemy)
+            .map(|s| (s.entity, s.translation, s.distance(position)))
+            .collect();
+        targets.sort_by(|a, b| a.2.total_cmp(&b.2));
+        // the grid is from the start of the frame, skip what was shot down since
+        let target = targets.into_iter().find(|(target, _, _)| {
+            cards
+                .get(*target)
+                .is_ok_and(|card| card.info.stats.health > 0)
+        });
+        let Some((target, translation, _)) = target else {
+            continue;
+        };
+        let Ok([tower_card, mut target_card]) = cards.get_many_mut([entity, target]) else {
+            continue;
+        };
+        let outcome = tower_card
+            .info
+            .stats
+            .attack(&target_card.combat_stats(), &mut rng);
+        target_card.info.stats.health =
+            (target_card.info.stats.health - outcome.damage() as isize).max(0);
+        let killed = target_card.info.stats.health == 0;
+        hits.send(CombatHit {
+            attacker: entity,
+            target,
+            outcome,
+            killed,
+            lunge: false,
+        });
+        if killed {
+            kill_card(
+                &mut commands,
+                &mut stack_roots,
+                &mut cards,
+                &mut deaths,
+                target,
+                translation,
+            );
+        }
+        tower.reload.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::game::card::evaluate_stacks;
+
+    fn recipe(card_types: &[(CardType, usize)]) -> Option<CardType> {
+        let card_types: HashMap<_, _> = card_types.iter().copied().collect();
+        Recipe::matching(&card_types).map(|recipe| recipe.result)
+    }
+
+    #[test]
+    fn recipes_take_one_villager_and_exactly_their_ingredients() {
+        let house = [(CardType::Villager, 1), (CardType::Log, 3)];
+        assert_eq!(recipe(&house), Some(CardType::House));
+
+        let extra_card = [
+            (CardType::Villager, 1),
+            (CardType::Log, 3),
+            (CardType::Berry, 1),
+        ];
+        assert_eq!(recipe(&extra_card), None);
+        assert_eq!(recipe(&[(CardType::Villager, 1), (CardType::Log, 4)]), None);
+        assert_eq!(recipe(&[(CardType::Villager, 2), (CardType::Log, 3)]), None);
+        assert_eq!(recipe(&[(CardType::Log, 3)]), None);
+    }
+
+    fn breeding_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<StackRoots>()
+            .init_resource::<Housing>()
+            .add_systems(
+                Update,
+                (count_population, evaluate_stacks.after(count_population)),
+            );
+        app
+    }
+
+    fn spawn_card(app: &mut App, card_type: CardType) -> Entity {
+        app.world
+            .spawn((Card::from(card_type), Transform::default()))
+            .id()
+    }
+
+    /// Spawns two villagers stacked on each other, ready to breed.
+    fn spawn_couple(app: &mut App) {
+        let bottom = spawn_card(app, CardType::Villager);
+        let top = spawn_card(app, CardType::Villager);
+        app.world.get_mut::<Card>(top).unwrap().stack_parent = Some(bottom);
+        app.world.get_mut::<Card>(bottom).unwrap().stack_child = Some(top);
+        app.world
+            .resource_mut::<StackRoots>()
+            .queue_recomputation(bottom);
+    }
+
+    /// Breeds for six seconds, long enough for one child per couple, and counts the villagers.
+    fn villagers_after_breeding(app: &mut App) -> usize {
+        app.update();
+        for _ in 0..12 {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(500));
+            app.update();
+        }
+        app.world
+            .query::<&Card>()
+            .iter(&app.world)
+            .filter(|card| card.card_type() == CardType::Villager)
+            .count()
+    }
+
+    #[test]
+    fn couples_breed_while_there_is_room() {
+        let mut app = breeding_app();
+        spawn_couple(&mut app);
+        assert_eq!(villagers_after_breeding(&mut app), 3);
+    }
+
+    #[test]
+    fn breeding_stops_once_the_houses_are_full() {
+        let mut app = breeding_app();
+        spawn_couple(&mut app);
+        spawn_couple(&mut app);
+        assert_eq!(villagers_after_breeding(&mut app), Housing::BASE_CAPACITY);
+    }
+
+    #[test]
+    fn two_couples_cannot_both_take_the_last_room() {
+        let mut app = breeding_app();
+        spawn_couple(&mut app);
+        spawn_couple(&mut app);
+        spawn_card(&mut app, CardType::Villager);
+        spawn_card(&mut app, CardType::House);
+        assert_eq!(
+            villagers_after_breeding(&mut app),
+            Housing::BASE_CAPACITY + Housing::PER_HOUSE
+        );
+    }
+}
@@ -4,11 +4,24 @@ use bevy::prelude::{Rectangle, *};
 use bevy::utils::{Entry, HashMap, HashSet};
 use bevy::window::PrimaryWindow;
 use bevy_rapier3d::prelude::*;
+use rand::Rng;
 
 use crate::game::animate::{AnimateRange, Ease};
+use crate::game::buildings::{Housing, Recipe};
 use crate::game::camera::PlayerCamera;
+use crate::game::combat::CombatHit;
+use crate::game::day_cycle::DayCycle;
+use crate::game::economy::pays;
+use crate::game::equipment::{Equipment, EquipmentSlot};
+use crate::game::exploration::FogTile;
+use crate::game::label::{set_text, WorldLabelBundle};
+use crate::game::loot::CardFlight;
+use crate::game::pack::PackTheme;
 use crate::game::progress_bar::{ProgressBar, ProgressBarBundle};
-use crate::game::tile::{HoveredTile, Tile};
+use crate::game::rng::GameRng;
+use crate::game::status::{Infliction, StatusEffects, StatusKind};
+use crate::game::tile::{start_construction, HoveredTile, Tile, TileConstruction, TileSlots};
+use crate::game::villager::{Identity, Newborn};
 
 pub struct CardPlugin;
 
@@ -18,7 +31,9 @@ impl Plugin for CardPlugin {
             .init_resource::<HoverPoint>()
             .init_resource::<StackRoots>()
             .init_resource::<CardData>()
+            .add_event::<CardDied>()
             .add_systems(PostUpdate, on_spawn_card)
+            .add_systems(PostUpdate, show_card_faces.after(on_spawn_card))
             .add_systems(Update, collide_cards)
             .add_systems(
                 Update,
@@ -28,9 +43,16 @@ impl Plugin for CardPlugin {
             )
             .add_systems(Update, move_cards.after(select_card))
             .add_systems(Update, evaluate_stacks.after(move_cards))
-            .add_systems(Update, handle_enemies.after(evaluate_stacks))
-            .add_systems(Update, combat.after(handle_enemies))
-            .add_systems(Update, set_hearts.after(combat));
+            .add_systems(Update, combat.after(crate::game::combat::form_fights))
+            .add_systems(Update, set_hearts.after(combat))
+            .add_systems(Update, label_card_stats.after(combat))
+            .add_systems(
+                Update,
+                animate_attacks
+                    .after(combat)
+                    .after(move_cards)
+                    .after(crate::game::combat::arrange_fights),
+            );
     }
 }
 
@@ -40,9 +62,17 @@ pub struct Card {
     pub info: CardInfo,
     pub z: usize,
     pub combat_state: Option<CombatState>,
+    pub combat_zone: Option<Entity>,
+    pub effects: StatusEffects,
+    pub equipment: Equipment,
+    /// Name, traits and level of a villager, given to it once it is spawned.
+    pub identity: Option<Identity>,
     pub stack_parent: Option<Entity>,
     pub stack_child: Option<Entity>,
     pub slotted_in_tile: Option<Entity>,
+    pub carried_by: Option<Entity>,
+    /// Whether a building was put down by the player, after which it stays where it is.
+    pub placed: bool,
 }
 
 pub struct CombatState {
@@ -50,6 +80,29 @@ pub struct CombatState {
     target: Entity,
 }
 
+impl CombatState {
+    pub fn new(target: Entity, cooldown: f32) -> Self {
+        Self {
+            cooldown: Timer::from_seconds(cooldown, TimerMode::Repeating),
+            target,
+        }
+    }
+
+    pub fn target(&self) -> Entity {
+        self.target
+    }
+
+    /// Switches to another target without resetting the cooldown.
+    pub fn retarget(&mut self, target: Entity) {
+        self.target = target;
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: f32) {
+        self.cooldown
+            .set_duration(Duration::from_secs_f32(cooldown));
+    }
+}
+
 impl From<CardType> for Card {
     fn from(card_type: CardType) -> Self {
         Self {
@@ -65,6 +118,8 @@ impl Card {
     pub const ART_HEIGHT: f32 = 166.0;
     pub const ART_ASPECT: f32 = Self::ART_WIDTH / Self::ART_HEIGHT;
     pub const SPAWN_OFFSET: f32 = 1.0;
+    /// How close two cards have to be to fight in melee.
+    pub const MELEE_REACH: f32 = 1.0;
 
     pub fn card_type(&self) -> CardType {
         self.info.card_type
@@ -75,7 +130,10 @@ impl Card {
     }
 
     pub fn is_stackable(&self) -> bool {
-        self.slotted_in_tile.is_none() && !(self.class() == CardClass::Enemy)
+        self.slotted_in_tile.is_none()
+            && self.carried_by.is_none()
+            && self.combat_zone.is_none()
+            && !matches!(self.class(), CardClass::Enemy | CardClass::Building)
     }
 
     pub fn is_player_controlled(&self) -> bool {
@@ -83,9 +141,31 @@ impl Card {
             CardClass::Villager => true,
             CardClass::Resource => true,
             CardClass::Enemy => false,
+            // buildings can be moved until they are first put down
+            CardClass::Building => !self.placed,
         }
     }
 
+    /// The stats the card fights with, including what the items, traits, level and profession
+    /// of a villager add.
+    pub fn combat_stats(&self) -> CardStats {
+        let mut stats = self.info.stats.clone();
+        let profession = self.identity.as_ref().and_then(|i| i.profession);
+        self.equipment.add_to(&mut stats, profession);
+        if let Some(identity) = &self.identity {
+            identity.add_to(&mut stats);
+        }
+        if let Some(profession) = profession {
+            profession.combat_bonus().add_to(&mut stats);
+        }
+        stats
+    }
+
+    /// Whether the card is a villager too young to work.
+    pub fn is_juvenile(&self) -> bool {
+        self.identity.as_ref().is_some_and(Identity::is_juvenile)
+    }
+
     pub fn in_stack(&self) -> bool {
         self.stack_parent.is_some() || self.stack_child.is_some()
     }
@@ -96,7 +176,22 @@ pub enum CardType {
     #[default]
     Villager,
     Log,
+    Berry,
     Goblin,
+    Thief,
+    Archer,
+    Brute,
+    Warlord,
+    Sword,
+    Shield,
+    Axe,
+    Hoe,
+    Coin,
+    Pack(PackTheme),
+    Grave,
+    House,
+    Storage,
+    Watchtower,
 }
 
 pub struct CardInfo {
@@ -120,9 +215,22 @@ impl From<CardType> for CardInfo {
 impl CardType {
     pub fn class(&self) -> CardClass {
         match self {
-            CardType::Villager { .. } => CardClass::Villager,
-            CardType::Log => CardClass::Resource,
-            CardType::Goblin { .. } => CardClass::Enemy,
+            CardType::Villager => CardClass::Villager,
+            CardType::Log
+            | CardType::Berry
+            | CardType::Sword
+            | CardType::Shield
+            | CardType::Axe
+            | CardType::Hoe
+            | CardType::Coin
+            | CardType::Pack(_)
+            | CardType::Grave => CardClass::Resource,
+            CardType::Goblin
+            | CardType::Thief
+            | CardType::Archer
+            | CardType::Brute
+            | CardType::Warlord => CardClass::Enemy,
+            CardType::House | CardType::Storage | CardType::Watchtower => CardClass::Building,
         }
     }
 
@@ -132,33 +240,227 @@ impl CardType {
                 health: 3,
                 max_health: 3,
                 damage: 1,
+                dodge: 0.1,
+                crit_chance: 0.1,
+                attack_speed: 1.1,
+                ..default()
             },
             CardType::Goblin => CardStats {
                 health: 1,
                 max_health: 1,
                 damage: 1,
+                dodge: 0.1,
+                crit_chance: 0.05,
+                ..default()
             },
-            _ => CardStats {
-                health: 0,
-                max_health: 0,
-                damage: 0,
+            CardType::Thief => CardStats {
+                health: 1,
+                max_health: 1,
+                damage: 1,
+                dodge: 0.3,
+                crit_chance: 0.05,
+                inflicts: Some(Infliction {
+                    kind: StatusKind::Poison,
+                    chance: 0.3,
+                }),
+                ..default()
             },
+            CardType::Archer => CardStats {
+                health: 1,
+                max_health: 1,
+                damage: 1,
+                dodge: 0.1,
+                crit_chance: 0.2,
+                attack_speed: 0.67,
+                inflicts: Some(Infliction {
+                    kind: StatusKind::Bleed,
+                    chance: 0.3,
+                }),
+                ..default()
+            },
+            CardType::Brute => CardStats {
+                health: 4,
+                max_health: 4,
+                damage: 2,
+                armor: 1,
+                crit_chance: 0.05,
+                attack_speed: 0.6,
+                inflicts: Some(Infliction {
+                    kind: StatusKind::Stun,
+                    chance: 0.25,
+                }),
+                ..default()
+            },
+            CardType::Warlord => CardStats {
+                health: 10,
+                max_health: 10,
+                damage: 2,
+                armor: 1,
+                dodge: 0.05,
+                crit_chance: 0.15,
+                attack_speed: 0.67,
+                inflicts: Some(Infliction {
+                    kind: StatusKind::Bleed,
+                    chance: 0.2,
+                }),
+                ..default()
+            },
+            CardType::Watchtower => CardStats {
+                damage: 1,
+                crit_chance: 0.1,
+                attack_speed: 0.5,
+                ..default()
+            },
+            CardType::Log
+            | CardType::Berry
+            | CardType::Sword
+            | CardType::Shield
+            | CardType::Axe
+            | CardType::Hoe
+            | CardType::Coin
+            | CardType::Pack(_)
+            | CardType::Grave
+            | CardType::House
+            | CardType::Storage => CardStats::default(),
+        }
+    }
+
+    /// How many coins the card is worth at the market, `None` if it can't be sold.
+    pub fn sell_value(&self) -> Option<usize> {
+        match self {
+            CardType::Villager | CardType::Sword | CardType::Shield => Some(3),
+            CardType::Axe | CardType::Hoe => Some(2),
+            CardType::Log | CardType::Berry => Some(1),
+            CardType::Goblin
+            | CardType::Thief
+            | CardType::Archer
+            | CardType::Brute
+            | CardType::Warlord
+            | CardType::Coin
+            | CardType::Pack(_)
+            | CardType::Grave
+            | CardType::House
+            | CardType::Storage
+            | CardType::Watchtower => None,
+        }
+    }
+
+    /// How much a villager is fed by eating this card at a feeding, if it is food.
+    pub fn food_value(&self) -> Option<usize> {
+        match self {
+            CardType::Berry => Some(1),
+            _ => None,
+        }
+    }
+
+    /// The effect a villager gets from eating this card, if it is food.
+    pub fn food_effect(&self) -> Option<StatusKind> {
+        match self {
+            CardType::Berry => Some(StatusKind::Regeneration),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CardStats {
     pub health: isize,
     pub max_health: usize,
     pub damage: usize,
+    /// Taken off the damage of every hit, which still deals at least 1.
+    pub armor: usize,
+    /// Chance to avoid a hit entirely.
+    pub dodge: f32,
+    pub crit_chance: f32,
+    pub crit_multiplier: f32,
+    /// Attacks per second.
+    pub attack_speed: f32,
+    /// Status effect the hits of this card may leave on the defender.
+    pub inflicts: Option<Infliction>,
 }
 
-#[derive(PartialEq, Eq)]
+impl Default for CardStats {
+    fn default() -> Self {
+        Self {
+            health: 0,
+            max_health: 0,
+            damage: 0,
+            armor: 0,
+            dodge: 0.0,
+            crit_chance: 0.0,
+            crit_multiplier: 2.0,
+            attack_speed: 1.0,
+            inflicts: None,
+        }
+    }
+}
+
+impl CardStats {
+    pub fn attack_cooldown(&self) -> f32 {
+        1.0 / self.attack_speed.max(0.01)
+    }
+
+    /// Rolls an attack against `defender`. The outcome only depends on the stats and `rng`, so
+    /// fights replay the same way from the same seed.
+    pub fn attack(&self, defender: &CardStats, rng: &mut GameRng) -> AttackOutcome {
+        if rng.gen::<f32>() < defender.dodge {
+            return AttackOutcome::Dodged;
+        }
+        let critical = rng.gen::<f32>() < self.crit_chance;
+        let damage = if critical {
+            (self.damage as f32 * self.crit_multiplier).round() as usize
+        } else {
+            self.damage
+        };
+        AttackOutcome::Hit {
+            damage: damage.saturating_sub(defender.armor).max(1),
+            critical,
+        }
+    }
+
+    /// A short line up of the fighting stats, for the label on the card.
+    pub fn summary(&self) -> String {
+        let mut value = format!("{} dmg  {:.1}/s", self.damage, self.attack_speed);
+        if self.armor > 0 {
+            value.push_str(&format!("  {} arm", self.armor));
+        }
+        value.push_str(&format!(
+            "\n{:.0}% dodge  {:.0}% crit",
+            self.dodge * 100.0,
+            self.crit_chance * 100.0
+        ));
+        if let Some(inflicts) = self.inflicts {
+            value.push_str(&format!(
+                "  {:.0}% {}",
+                inflicts.chance * 100.0,
+                inflicts.kind.name()
+            ));
+        }
+        value
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AttackOutcome {
+    Dodged,
+    Hit { damage: usize, critical: bool },
+}
+
+impl AttackOutcome {
+    pub fn damage(&self) -> usize {
+        match self {
+            AttackOutcome::Dodged => 0,
+            AttackOutcome::Hit { damage, .. } => *damage,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum CardClass {
     Villager,
     Resource,
     Enemy,
+    Building,
 }
 
 #[derive(Default, PartialEq, Eq, Copy, Clone, Resource)]
@@ -169,7 +471,7 @@ pub enum SelectedCard {
 }
 
 impl SelectedCard {
-    fn is_selected(self, entity: Entity) -> bool {
+    pub fn is_selected(self, entity: Entity) -> bool {
         match self {
             SelectedCard::Some(e) => e == entity,
             SelectedCard::None => false,
@@ -202,7 +504,27 @@ pub struct CardBundle {
 pub enum StackType {
     Pending,
     Nothing,
-    Breed { progress_bar: Entity },
+    /// `notice` is a label on the progress bar telling why breeding is on hold.
+    Breed {
+        progress_bar: Entity,
+        notice: Entity,
+    },
+    Build {
+        result: CardType,
+        progress_bar: Entity,
+    },
+}
+
+impl StackType {
+    /// The progress bar shown on the stack's root while it works towards something.
+    pub fn progress_bar(&self) -> Option<Entity> {
+        match self {
+            StackType::Pending | StackType::Nothing => None,
+            StackType::Breed { progress_bar, .. } | StackType::Build { progress_bar, .. } => {
+                Some(*progress_bar)
+            }
+        }
+    }
 }
 
 #[derive(Default, Resource)]
@@ -211,6 +533,19 @@ pub struct StackRoots {
     queued_stack_recomputations: HashSet<Entity>,
 }
 
+impl StackRoots {
+    /// Has the stack the entity is part of evaluated again, after something about its cards
+    /// changed.
+    pub fn queue_recomputation(&mut self, entity: Entity) {
+        self.queued_stack_recomputations.insert(entity);
+    }
+
+    /// The bottom cards of all stacks.
+    pub fn roots(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.roots.keys().copied()
+    }
+}
+
 impl Default for CardBundle {
     fn default() -> Self {
         Self {
@@ -236,11 +571,29 @@ pub struct CardData {
     villager_base: Handle<StandardMaterial>,
     resource_base: Handle<StandardMaterial>,
     enemy_base: Handle<StandardMaterial>,
+    building_base: Handle<StandardMaterial>,
     villager_portrait_base: Handle<StandardMaterial>,
     log_portrait_base: Handle<StandardMaterial>,
+    berry_portrait_base: Handle<StandardMaterial>,
     goblin_portrait_base: Handle<StandardMaterial>,
+    thief_portrait_base: Handle<StandardMaterial>,
+    archer_portrait_base: Handle<StandardMaterial>,
+    brute_portrait_base: Handle<StandardMaterial>,
+    warlord_portrait_base: Handle<StandardMaterial>,
+    sword_portrait_base: Handle<StandardMaterial>,
+    shield_portrait_base: Handle<StandardMaterial>,
+    axe_portrait_base: Handle<StandardMaterial>,
+    hoe_portrait_base: Handle<StandardMaterial>,
+    coin_portrait_base: Handle<StandardMaterial>,
+    grave_portrait_base: Handle<StandardMaterial>,
+    house_portrait_base: Handle<StandardMaterial>,
+    storage_portrait_base: Handle<StandardMaterial>,
+    watchtower_portrait_base: Handle<StandardMaterial>,
+    pack_portrait_bases: Vec<(PackTheme, Handle<StandardMaterial>)>,
+    back_material: Handle<StandardMaterial>,
     heart_material: Handle<StandardMaterial>,
     removed_heart_material: Handle<StandardMaterial>,
+    hit_material: Handle<StandardMaterial>,
 }
 
 impl FromWorld for CardData {
@@ -267,6 +620,10 @@ impl FromWorld for CardData {
             base_color: Color::rgb(0.7, 0.4, 0.4),
             ..card_base_material.clone()
         };
+        let building_base = StandardMaterial {
+            base_color: Color::rgb(0.45, 0.55, 0.7),
+            ..card_base_material.clone()
+        };
         Self {
             mesh: meshes.add(Rectangle {
                 half_size: Vec2::new(Card::ASPECT_RATIO, 1.0) / 2.0,
@@ -288,10 +645,81 @@ impl FromWorld for CardData {
                 base_color_texture: Some(asset_server.load("log.png")),
                 ..resource_base.clone()
             }),
+            berry_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("berry.png")),
+                ..resource_base.clone()
+            }),
             goblin_portrait_base: materials.add(StandardMaterial {
                 base_color_texture: Some(asset_server.load("goblin.png")),
                 ..enemy_base.clone()
             }),
+            thief_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("thief.png")),
+                ..enemy_base.clone()
+            }),
+            archer_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("archer.png")),
+                ..enemy_base.clone()
+            }),
+            brute_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("brute.png")),
+                ..enemy_base.clone()
+            }),
+            warlord_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("warlord.png")),
+                ..enemy_base.clone()
+            }),
+            sword_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("sword.png")),
+                ..resource_base.clone()
+            }),
+            shield_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("shield.png")),
+                ..resource_base.clone()
+            }),
+            axe_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("axe.png")),
+                ..resource_base.clone()
+            }),
+            hoe_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("hoe.png")),
+                ..resource_base.clone()
+            }),
+            coin_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("coin.png")),
+                ..resource_base.clone()
+            }),
+            grave_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("grave.png")),
+                ..resource_base.clone()
+            }),
+            house_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("house.png")),
+                ..building_base.clone()
+            }),
+            storage_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("storage.png")),
+                ..building_base.clone()
+            }),
+            watchtower_portrait_base: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("watchtower.png")),
+                ..building_base.clone()
+            }),
+            pack_portrait_bases: PackTheme::ALL
+                .iter()
+                .map(|theme| {
+                    let material = materials.add(StandardMaterial {
+                        base_color: theme.color(),
+                        base_color_texture: Some(asset_server.load("pack.png")),
+                        ..resource_base.clone()
+                    });
+                    (*theme, material)
+                })
+                .collect(),
+            back_material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.3, 0.35, 0.55),
+                ..card_base_material.clone()
+            }),
             heart_material: materials.add(StandardMaterial {
                 base_color: Color::rgba_u8(200, 90, 90, 255),
                 base_color_texture: Some(asset_server.load("heart.png")),
@@ -308,9 +736,14 @@ impl FromWorld for CardData {
                 depth_bias: 0.1,
                 ..default()
             }),
+            hit_material: materials.add(StandardMaterial {
+                base_color: Color::rgb(1.0, 0.85, 0.85),
+                ..card_base_material.clone()
+            }),
             villager_base: materials.add(villager_base),
             resource_base: materials.add(resource_base),
             enemy_base: materials.add(enemy_base),
+            building_base: materials.add(building_base),
         }
     }
 }
@@ -321,13 +754,34 @@ impl CardData {
             CardClass::Villager => self.villager_base.clone(),
             CardClass::Resource => self.resource_base.clone(),
             CardClass::Enemy => self.enemy_base.clone(),
+            CardClass::Building => self.building_base.clone(),
         }
     }
     pub fn portrait_material(&self, card_type: CardType) -> Handle<StandardMaterial> {
         match card_type {
-            CardType::Villager { .. } => self.villager_portrait_base.clone(),
+            CardType::Villager => self.villager_portrait_base.clone(),
             CardType::Log => self.log_portrait_base.clone(),
-            CardType::Goblin { .. } => self.goblin_portrait_base.clone(),
+            CardType::Berry => self.berry_portrait_base.clone(),
+            CardType::Goblin => self.goblin_portrait_base.clone(),
+            CardType::Thief => self.thief_portrait_base.clone(),
+            CardType::Archer => self.archer_portrait_base.clone(),
+            CardType::Brute => self.brute_portrait_base.clone(),
+            CardType::Warlord => self.warlord_portrait_base.clone(),
+            CardType::Sword => self.sword_portrait_base.clone(),
+            CardType::Shield => self.shield_portrait_base.clone(),
+            CardType::Axe => self.axe_portrait_base.clone(),
+            CardType::Hoe => self.hoe_portrait_base.clone(),
+            CardType::Coin => self.coin_portrait_base.clone(),
+            CardType::Grave => self.grave_portrait_base.clone(),
+            CardType::Pack(theme) => self
+                .pack_portrait_bases
+                .iter()
+                .find(|(t, _)| *t == theme)
+                .map(|(_, material)| material.clone())
+                .unwrap_or_default(),
+            CardType::House => self.house_portrait_base.clone(),
+            CardType::Storage => self.storage_portrait_base.clone(),
+            CardType::Watchtower => self.watchtower_portrait_base.clone(),
         }
     }
 }
@@ -336,12 +790,23 @@ const HEART_WIDTH: f32 = 0.11;
 const HEART_HEIGHT: f32 = 0.1;
 const HEART_PANEL_WIDTH: f32 = 0.6;
 
-fn on_spawn_card(
+pub fn on_spawn_card(
     mut commands: Commands,
     card_data: Res<CardData>,
     cards: Query<(Entity, &Card), Added<Card>>,
 ) {
     for (entity, card) in &cards {
+        if card.info.stats.max_health > 0 {
+            let label = commands
+                .spawn(WorldLabelBundle::new(
+                    entity,
+                    Vec3::new(0.0, -0.45, 0.0),
+                    11.0,
+                    Color::rgb(0.95, 0.95, 0.9),
+                ))
+                .id();
+            commands.entity(entity).insert(CardStatsLabel(label));
+        }
         commands.entity(entity).with_children(|parent| {
             parent.spawn(PbrBundle {
                 material: card_data.class_material(card.class()),
@@ -377,6 +842,67 @@ fn on_spawn_card(
     }
 }
 
+/// A card lying with its back up, hiding its portrait, hearts and stats until it is turned.
+#[derive(Component)]
+pub struct FaceDown;
+
+fn show_card_faces(
+    card_data: Res<CardData>,
+    turned_down: Query<Entity, Added<FaceDown>>,
+    mut turned_up: RemovedComponents<FaceDown>,
+    cards: Query<(&Card, &Children)>,
+    mut materials: Query<&mut Handle<StandardMaterial>>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let turned = turned_down
+        .iter()
+        .map(|entity| (entity, true))
+        .chain(turned_up.read().map(|entity| (entity, false)));
+    for (entity, face_down) in turned {
+        let Ok((card, children)) = cards.get(entity) else {
+            continue;
+        };
+        if let Ok(mut material) = materials.get_mut(children[0]) {
+            *material = if face_down {
+                card_data.back_material.clone()
+            } else {
+                card_data.class_material(card.class())
+            };
+        }
+        for child in [children[1], children[2]] {
+            if let Ok(mut visibility) = visibilities.get_mut(child) {
+                *visibility = if face_down {
+                    Visibility::Hidden
+                } else {
+                    Visibility::Inherited
+                };
+            }
+        }
+    }
+}
+
+/// The label showing the fighting stats of a card.
+#[derive(Component)]
+pub struct CardStatsLabel(pub Entity);
+
+fn label_card_stats(
+    cards: Query<(&Card, &CardStatsLabel, Has<FaceDown>)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (card, label, face_down) in &cards {
+        if let Ok(mut text) = texts.get_mut(label.0) {
+            let value = match (&card.identity, face_down) {
+                (_, true) => String::new(),
+                (Some(identity), false) => {
+                    format!("{}\n{}", identity.title(), card.combat_stats().summary())
+                }
+                (None, false) => card.combat_stats().summary(),
+            };
+            set_text(&mut text, &value);
+        }
+    }
+}
+
 fn set_hearts(
     card_data: Res<CardData>,
     cards: Query<(&Card, &Children)>,
@@ -399,13 +925,14 @@ fn set_hearts(
     }
 }
 
-fn move_cards(
+pub fn move_cards(
     time: Res<Time>,
     selected: Res<SelectedCard>,
     hover_point: Res<HoverPoint>,
     stack_roots: Res<StackRoots>,
     mut cards: Query<(Entity, &mut Card, &mut Transform)>,
     mut transforms: Query<&Transform, Without<Card>>,
+    tile_slots: Query<&TileSlots>,
 ) {
     for (entity, mut card, mut transform) in &mut cards {
         let mut z_offset = 0.0;
@@ -420,9 +947,16 @@ fn move_cards(
         }
 
         if let Some(tile) = card.slotted_in_tile {
-            let tile_transform = transforms.get(tile).unwrap();
-            transform.translation.x = tile_transform.translation.x;
-            transform.translation.y = tile_transform.translation.y;
+            if let (Ok(tile_transform), Ok(tile_slots)) =
+                (transforms.get(tile), tile_slots.get(tile))
+            {
+                let offset = tile_slots.card_offset(entity).unwrap_or_default();
+                transform.translation.x = tile_transform.translation.x + offset.x;
+                transform.translation.y = tile_transform.translation.y + offset.y;
+            } else {
+                // the tile was replaced or removed, release the card
+                card.slotted_in_tile = None;
+            }
         }
         transform.translation.z = z_offset;
     }
@@ -464,12 +998,18 @@ fn collide_cards(
     mut selected: Res<SelectedCard>,
     mut cards: Query<&mut Card>,
     transforms: Query<&Transform>,
+    flights: Query<(), With<CardFlight>>,
 ) {
     let mut stack_x_on_y = Vec::new();
     for collision in collisions.read() {
         match *collision {
             CollisionEvent::Started(e1, e2, _) => {
-                if selected.is_selected(e1) || selected.is_selected(e2) {
+                // cards only stack once they have landed
+                if selected.is_selected(e1)
+                    || selected.is_selected(e2)
+                    || flights.contains(e1)
+                    || flights.contains(e2)
+                {
                     continue;
                 }
                 if let (Ok([mut c1, mut c2]), Ok([t1, t2])) =
@@ -541,6 +1081,101 @@ fn find_stack_top(cards: &Query<&Card>, mut current_entity: Entity) -> Entity {
     }
 }
 
+/// All cards of the stack starting at `root`, from the bottom up.
+pub fn stack_cards(cards: &Query<&Card>, root: Entity) -> Vec<Entity> {
+    let mut current = root;
+    let mut stack = Vec::new();
+    while let Ok(card) = cards.get(current) {
+        stack.push(current);
+        if let Some(child) = card.stack_child {
+            current = child;
+        } else {
+            break;
+        }
+    }
+    stack
+}
+
+/// Sent when a card runs out of health, right before it is despawned.
+#[derive(Event)]
+pub struct CardDied {
+    pub entity: Entity,
+    pub card_type: CardType,
+    pub translation: Vec3,
+    /// The items it had equipped.
+    pub equipment: Vec<CardType>,
+}
+
+/// Despawns a card that ran out of health and tells everyone about it.
+pub fn kill_card(
+    commands: &mut Commands,
+    stack_roots: &mut StackRoots,
+    cards: &mut Query<&mut Card>,
+    deaths: &mut EventWriter<CardDied>,
+    entity: Entity,
+    translation: Vec3,
+) {
+    if let Ok(mut card) = cards.get_mut(entity) {
+        deaths.send(CardDied {
+            entity,
+            card_type: card.card_type(),
+            translation,
+            equipment: card.equipment.take(),
+        });
+    }
+    despawn_card(commands, stack_roots, cards, entity);
+}
+
+/// Takes a card out of its stack and despawns it.
+pub fn despawn_card(
+    commands: &mut Commands,
+    stack_roots: &mut StackRoots,
+    cards: &mut Query<&mut Card>,
+    entity: Entity,
+) {
+    // the progress bar of the stack type is a child of the root and despawns with it
+    unstack_card(commands, stack_roots, cards, entity);
+    commands.entity(entity).despawn_recursive();
+}
+
+/// Takes a card out of its stack. The cards above and below it are linked together and the
+/// stack they form is recomputed.
+pub fn unstack_card(
+    commands: &mut Commands,
+    stack_roots: &mut StackRoots,
+    cards: &mut Query<&mut Card>,
+    entity: Entity,
+) {
+    let Ok(mut card) = cards.get_mut(entity) else {
+        return;
+    };
+    let (parent, child) = (card.stack_parent.take(), card.stack_child.take());
+    if let Some(parent) = parent {
+        if let Ok(mut parent_card) = cards.get_mut(parent) {
+            parent_card.stack_child = child;
+        }
+        stack_roots.queued_stack_recomputations.insert(parent);
+    }
+    if let Some(child) = child {
+        if let Ok(mut child_card) = cards.get_mut(child) {
+            child_card.stack_parent = parent;
+        }
+        // the card above a removed root becomes the new root
+        if parent.is_none() {
+            stack_roots.roots.insert(child, StackType::Pending);
+            stack_roots.queued_stack_recomputations.insert(child);
+        }
+    }
+    if let Some(progress_bar) = stack_roots
+        .roots
+        .remove(&entity)
+        .and_then(|stack_type| stack_type.progress_bar())
+    {
+        commands.entity(progress_bar).despawn_recursive();
+    }
+    stack_roots.queued_stack_recomputations.remove(&entity);
+}
+
 fn find_stack_root(cards: &Query<&Card>, mut current_entity: Entity) -> Entity {
     loop {
         if let Ok(card) = cards.get(current_entity) {
@@ -560,13 +1195,17 @@ pub fn select_card(
     context: Res<RapierContext>,
     windows: Query<&Window, With<PrimaryWindow>>,
     hovered_tile: Res<HoveredTile>,
+    day_cycle: Res<DayCycle>,
     mouse: Res<ButtonInput<MouseButton>>,
     mut selected_card: ResMut<SelectedCard>,
     mut stack_roots: ResMut<StackRoots>,
     mut hover_point: ResMut<HoverPoint>,
     cameras: Query<(&Camera, &Transform), With<PlayerCamera>>,
     mut cards: Query<&mut Card>,
-    mut tiles: Query<(&mut Tile, &Transform)>,
+    mut tiles: Query<(&mut TileSlots, &Transform)>,
+    upgradable_tiles: Query<&Tile, Without<TileConstruction>>,
+    mut fogs: Query<&mut FogTile>,
+    flights: Query<(), With<CardFlight>>,
 ) {
     let window = windows.single();
     if let Some(mut cursor) = window.cursor_position() {
@@ -606,26 +1245,19 @@ pub fn select_card(
             let result = context.cast_ray(near, direction, 50.0, true, QueryFilter::new());
 
             if let Some((entity, toi)) = result {
-                if cards.get(entity).unwrap().is_player_controlled() {
+                // cards in the air can't be caught
+                if cards.get(entity).unwrap().is_player_controlled() && !flights.contains(entity) {
                     let (parent, child) = {
                         let mut card = cards.get_mut(entity).unwrap();
                         // unslot from tile
                         if let Some(tile_entity) = card.slotted_in_tile {
                             card.slotted_in_tile = None;
-                            let (mut tile, _) = tiles.get_mut(tile_entity).unwrap();
-                            match &mut *tile {
-                                Tile::Woods {
-                                    slotted_villager,
-                                    progress_bar,
-                                } => {
-                                    *slotted_villager = None;
-                                    if let Some(progress_bar) = *progress_bar {
-                                        commands.entity(progress_bar).despawn_recursive();
-                                    }
-                                }
-                                _ => {}
+                            if let Ok((mut tile_slots, _)) = tiles.get_mut(tile_entity) {
+                                tile_slots.unslot_card(entity);
                             }
                         }
+                        // snatch it back from a thief
+                        card.carried_by = None;
                         card.animations.select.reset();
                         *selected_card = SelectedCard::Some(entity);
                         let parent = card.stack_parent;
@@ -652,24 +1284,85 @@ pub fn select_card(
 
     if mouse.just_released(MouseButton::Left) {
         if let SelectedCard::Some(entity) = *selected_card {
-            let mut card = cards.get_mut(entity).unwrap();
-            card.animations.deselect.reset();
             *selected_card = SelectedCard::None;
+            // it may have died while it was held
+            let Ok(mut card) = cards.get_mut(entity) else {
+                return;
+            };
+            card.animations.deselect.reset();
+            if card.class() == CardClass::Building {
+                card.placed = true;
+            }
             // try stacking on a tile
             if !card.in_stack() {
                 if let Some(tile_entity) = hovered_tile.0 {
-                    if let Ok((mut tile, transform)) = tiles.get_mut(tile_entity) {
-                        if let HoverPoint::Some(hover_point) = *hover_point {
-                            let slot_size = Tile::slot_size();
-                            if transform.translation.x - slot_size.x / 2.0 < hover_point.x
-                                && hover_point.x < transform.translation.x + slot_size.x / 2.0
-                                && transform.translation.y - slot_size.y / 2.0 < hover_point.y
-                                && hover_point.y < transform.translation.y + slot_size.y / 2.0
-                            {
-                                if tile.try_slotting_card(&mut commands, tile_entity, entity, &card)
-                                {
-                                    card.slotted_in_tile = Some(tile_entity);
-                                }
+                    if let HoverPoint::Some(hover_point) = *hover_point {
+                        if let Ok((mut tile_slots, transform)) = tiles.get_mut(tile_entity) {
+                            if tile_slots.try_slotting_card(
+                                transform.translation,
+                                hover_point,
+                                entity,
+                                &card,
+                            ) {
+                                card.slotted_in_tile = Some(tile_entity);
+                            }
+                        }
+                    }
+                }
+            } else if let Some(tile_entity) = hovered_tile.0 {
+                let stack = stack_cards(&cards.to_readonly(), entity);
+                let card_types: Vec<_> = stack
+                    .iter()
+                    .filter_map(|e| cards.get(*e).ok())
+                    .map(|card| card.card_type())
+                    .collect();
+                // a whole stack dropped on a tile pays for one thing: its upgrade, an unexplored
+                // tile or a pack at the market
+                let price = if let Some(upgrade) = upgradable_tiles
+                    .get(tile_entity)
+                    .ok()
+                    .and_then(|tile| tile.upgrade())
+                    .filter(|upgrade| upgrade.accepts(&card_types))
+                {
+                    start_construction(&mut commands, tile_entity, upgrade);
+                    Some(upgrade.amount)
+                } else if let Some(mut fog) = fogs
+                    .get_mut(tile_entity)
+                    .ok()
+                    .filter(|_| pays(&card_types, FogTile::PRICE))
+                {
+                    fog.bought = true;
+                    Some(FogTile::PRICE)
+                } else if let (Ok(Tile::Market), Ok((_, transform)), Some(theme)) = (
+                    upgradable_tiles.get(tile_entity),
+                    tiles.get(tile_entity),
+                    PackTheme::best_affordable(&card_types, day_cycle.day),
+                ) {
+                    commands.spawn(CardBundle {
+                        card: Card::from(CardType::Pack(theme)),
+                        transform: Transform::from_translation(Tile::spawn_point(
+                            transform.translation,
+                        )),
+                        ..default()
+                    });
+                    Some(theme.price())
+                } else {
+                    None
+                };
+                // consume from the top, leftovers stay in hand as a smaller stack
+                for consumed in stack.iter().rev().take(price.unwrap_or(0)) {
+                    despawn_card(&mut commands, &mut stack_roots, &mut cards, *consumed);
+                }
+                // otherwise the cards of the stack fill whatever slots accept them
+                if let (None, Ok((mut tile_slots, _))) = (price, tiles.get_mut(tile_entity)) {
+                    for stacked in stack_cards(&cards.to_readonly(), entity) {
+                        let fits = cards
+                            .get(stacked)
+                            .is_ok_and(|card| tile_slots.try_slotting_anywhere(stacked, card));
+                        if fits {
+                            unstack_card(&mut commands, &mut stack_roots, &mut cards, stacked);
+                            if let Ok(mut card) = cards.get_mut(stacked) {
+                                card.slotted_in_tile = Some(tile_entity);
                             }
                         }
                     }
@@ -679,17 +1372,20 @@ pub fn select_card(
     }
 }
 
-fn evaluate_stacks(
+#[allow(clippy::too_many_arguments)]
+pub fn evaluate_stacks(
     mut commands: Commands,
     time: Res<Time>,
+    housing: Res<Housing>,
     mut stack_roots: ResMut<StackRoots>,
-    cards: Query<&Card>,
+    mut cards: Query<&mut Card>,
     mut progress_bars: Query<&mut ProgressBar>,
+    mut texts: Query<&mut Text>,
     transforms: Query<&Transform>,
 ) {
     let stack_roots = &mut *stack_roots;
     for entity in stack_roots.queued_stack_recomputations.drain() {
-        let root = find_stack_root(&cards, entity);
+        let root = find_stack_root(&cards.to_readonly(), entity);
         let mut cancelled_stack_types = Vec::new();
         if root != entity {
             // if the queued entity is no longer a root, remove the root and cancel the current stack_type
@@ -698,29 +1394,32 @@ fn evaluate_stacks(
             }
         }
         // if the queued root is still a root, recompute the stack type
-        let card_types = get_cards_types(root, &cards);
+        let card_types = get_cards_types(root, &cards.to_readonly());
         let villagers = card_types.get(&CardType::Villager).unwrap_or(&0);
-        let new_stack_type = if *villagers == 2 && card_types.len() == 1 {
-            let mut progress_bar = None;
-            commands.entity(root).with_children(|parent| {
-                progress_bar = Some(
-                    parent
-                        .spawn(ProgressBarBundle {
-                            progress_bar: ProgressBar {
-                                current: 0.0,
-                                total: 5.0,
-                                width: 0.7,
-                                height: 0.15,
-                                padding: 0.05,
-                            },
-                            transform: Transform::from_xyz(0.0, 0.55, 0.0),
-                            ..default()
-                        })
-                        .id(),
-                );
-            });
+        let juveniles = stack_cards(&cards.to_readonly(), root)
+            .into_iter()
+            .any(|entity| cards.get(entity).is_ok_and(|card| card.is_juvenile()));
+        // children neither breed nor build
+        let new_stack_type = if juveniles {
+            StackType::Nothing
+        } else if *villagers == 2 && card_types.len() == 1 {
+            let progress_bar = spawn_stack_progress_bar(&mut commands, root, 5.0);
+            let notice = commands
+                .spawn(WorldLabelBundle::new(
+                    progress_bar,
+                    Vec3::new(0.0, 0.2, 0.0),
+                    16.0,
+                    Color::rgb(1.0, 0.7, 0.4),
+                ))
+                .id();
             StackType::Breed {
-                progress_bar: progress_bar.unwrap(),
+                progress_bar,
+                notice,
+            }
+        } else if let Some(recipe) = Recipe::matching(&card_types) {
+            StackType::Build {
+                result: recipe.result,
+                progress_bar: spawn_stack_progress_bar(&mut commands, root, recipe.build_time),
             }
         } else {
             StackType::Nothing
@@ -732,33 +1431,70 @@ fn evaluate_stacks(
         }
 
         for stack_type in cancelled_stack_types {
-            match stack_type {
-                StackType::Pending => {}
-                StackType::Nothing => {}
-                StackType::Breed { progress_bar } => {
-                    commands.entity(progress_bar).despawn_recursive();
-                }
+            if let Some(progress_bar) = stack_type.progress_bar() {
+                commands.entity(progress_bar).despawn_recursive();
             }
         }
     }
 
     let mut queued_recomputations = Vec::new();
+    let mut finished_builds = Vec::new();
+    // counts the villagers born this frame, so that two stacks can't both take the last room
+    let mut population = housing.population;
     for (root, stack_type) in stack_roots.roots.iter_mut() {
         let mut should_reset = false;
         match stack_type {
             StackType::Pending => {}
             StackType::Nothing => {}
-            StackType::Breed { progress_bar } => {
+            StackType::Breed {
+                progress_bar,
+                notice,
+            } => {
+                let full = population >= housing.capacity;
+                if let Ok(mut text) = texts.get_mut(*notice) {
+                    let value = if full { "No room, build a house" } else { "" };
+                    set_text(&mut text, value);
+                }
+                if full {
+                    continue;
+                }
                 if let Ok(mut bar) = progress_bars.get_mut(*progress_bar) {
                     bar.add(time.delta_seconds());
                     if bar.finished() {
                         commands.entity(*progress_bar).despawn_recursive();
                         if let Ok(transform) = transforms.get(*root) {
-                            commands.spawn(CardBundle {
-                                card: Card {
-                                    info: CardType::Villager.into(),
+                            commands.spawn((
+                                CardBundle {
+                                    card: Card {
+                                        info: CardType::Villager.into(),
+                                        ..default()
+                                    },
+                                    transform: Transform::from_xyz(
+                                        transform.translation.x + Card::SPAWN_OFFSET,
+                                        transform.translation.y,
+                                        0.0,
+                                    ),
                                     ..default()
                                 },
+                                Newborn,
+                            ));
+                            population += 1;
+                        }
+                        should_reset = true;
+                    }
+                }
+            }
+            StackType::Build {
+                result,
+                progress_bar,
+            } => {
+                if let Ok(mut bar) = progress_bars.get_mut(*progress_bar) {
+                    bar.add(time.delta_seconds());
+                    if bar.finished() {
+                        commands.entity(*progress_bar).despawn_recursive();
+                        if let Ok(transform) = transforms.get(*root) {
+                            commands.spawn(CardBundle {
+                                card: Card::from(*result),
                                 transform: Transform::from_xyz(
                                     transform.translation.x + Card::SPAWN_OFFSET,
                                     transform.translation.y,
@@ -767,6 +1503,7 @@ fn evaluate_stacks(
                                 ..default()
                             });
                         }
+                        finished_builds.push(*root);
                         should_reset = true;
                     }
                 }
@@ -781,14 +1518,53 @@ fn evaluate_stacks(
     stack_roots
         .queued_stack_recomputations
         .extend(queued_recomputations);
+
+    // the builder and the items it carries are left over, the rest went into the building
+    for root in finished_builds {
+        for entity in stack_cards(&cards.to_readonly(), root) {
+            let used_up = cards.get(entity).is_ok_and(|card| {
+                card.class() != CardClass::Villager && EquipmentSlot::of(card.card_type()).is_none()
+            });
+            if used_up {
+                despawn_card(&mut commands, stack_roots, &mut cards, entity);
+            }
+        }
+    }
+}
+
+/// Spawns the progress bar shown above a stack while it works towards something.
+fn spawn_stack_progress_bar(commands: &mut Commands, root: Entity, total: f32) -> Entity {
+    let mut progress_bar = None;
+    commands.entity(root).with_children(|parent| {
+        progress_bar = Some(
+            parent
+                .spawn(ProgressBarBundle {
+                    progress_bar: ProgressBar {
+                        current: 0.0,
+                        total,
+                        width: 0.7,
+                        height: 0.15,
+                        padding: 0.05,
+                    },
+                    transform: Transform::from_xyz(0.0, 0.55, 0.0),
+                    ..default()
+                })
+                .id(),
+        );
+    });
+    progress_bar.unwrap()
 }
 
+/// Counts the card types in a stack. Items never take part in recipes, they are equipped by the
+/// villager they are dropped on instead.
 fn get_cards_types(root: Entity, cards: &Query<&Card>) -> HashMap<CardType, usize> {
     let mut current = root;
     let mut card_types = HashMap::new();
     while let Ok(card) = cards.get(current) {
-        let mut count = card_types.entry(card.card_type()).or_insert(0);
-        *count += 1;
+        if EquipmentSlot::of(card.card_type()).is_none() {
+            let mut count = card_types.entry(card.card_type()).or_insert(0);
+            *count += 1;
+        }
         if let Some(child) = card.stack_child {
             current = child;
         } else {
@@ -804,10 +1580,25 @@ pub struct Animations {
     deselect: AnimateRange,
     attack_in: AnimateRange,
     attack_out: AnimateRange,
+    hit: AnimateRange,
+    /// Direction of the attack being played, if any.
+    lunge: Option<Vec3>,
+    lunge_offset: Vec3,
+}
+
+impl Animations {
+    /// How far the card is currently moved out of its place by an attack.
+    pub fn lunge_offset(&self) -> Vec3 {
+        self.lunge_offset
+    }
 }
 
 impl Default for Animations {
     fn default() -> Self {
+        let mut hit =
+            AnimateRange::new(Duration::from_secs_f32(0.15), Ease::Linear, 1.0..0.0, false);
+        // cards don't flash when they spawn
+        hit.set_percent(1.0);
         Self {
             select: AnimateRange::new(Duration::from_secs_f32(0.2), Ease::Linear, 0.0..0.5, false),
             deselect: AnimateRange::new(
@@ -817,89 +1608,44 @@ impl Default for Animations {
                 false,
             ),
             attack_in: AnimateRange::new(
-                Duration::from_secs_f32(0.2),
+                Duration::from_secs_f32(0.1),
                 Ease::Linear,
-                1.0..1.5,
+                0.0..0.3,
                 false,
             ),
             attack_out: AnimateRange::new(
                 Duration::from_secs_f32(0.2),
                 Ease::Linear,
-                1.5..1.0,
+                0.3..0.0,
                 false,
             ),
+            hit,
+            lunge: None,
+            lunge_offset: Vec3::ZERO,
         }
     }
 }
 
-pub fn handle_enemies(time: Res<Time>, mut cards: Query<(Entity, &mut Card, &mut Transform)>) {
-    let mut enemy_targets = Vec::new();
-    for (entity, card, transform) in &cards {
-        if card.combat_state.is_some() {
-            continue;
-        }
-        if let CardClass::Enemy = card.class() {
-            let mut current_target: Option<(Entity, Vec3)> = None;
-            for (target_entity, target_card, target_transform) in &cards {
-                if target_card.class() == CardClass::Villager {
-                    if let Some((_, current_translation)) = current_target {
-                        if current_translation.distance_squared(transform.translation)
-                            > target_transform
-                                .translation
-                                .distance_squared(transform.translation)
-                        {
-                            current_target = Some((target_entity, target_transform.translation));
-                        }
-                    } else {
-                        current_target = Some((target_entity, target_transform.translation));
-                    }
-                }
-            }
-
-            if let Some((target, translation)) = current_target {
-                enemy_targets.push((entity, target, translation))
-            }
-        }
-    }
-
-    for (enemy, target, target_translation) in enemy_targets {
-        let [(_, mut card, mut transform), (_, mut target_card, _)] =
-            cards.get_many_mut([enemy, target]).unwrap();
-        let distance = target_translation - transform.translation;
-        // move until close
-        if distance.length() > 1.0 {
-            let direction = distance.normalize();
-            transform.translation += direction * time.delta_seconds();
-            card.combat_state = None;
-        } else {
-            card.combat_state = Some(CombatState {
-                cooldown: Timer::from_seconds(1.0, TimerMode::Repeating),
-                target,
-            });
-
-            // if target_card.combat_state.is_none() {
-            //     target_card.combat_state = Some(CombatState {
-            //         // villagers attack faster than enemies
-            //         cooldown: Timer::from_seconds(0.9, true),
-            //         target: enemy,
-            //     });
-            // }
-        }
-    }
-}
-
-fn combat(
+#[allow(clippy::too_many_arguments)]
+pub fn combat(
     mut commands: Commands,
     time: Res<Time>,
+    mut hits: EventWriter<CombatHit>,
+    mut deaths: EventWriter<CardDied>,
+    mut rng: ResMut<GameRng>,
+    mut stack_roots: ResMut<StackRoots>,
     mut cards: Query<&mut Card>,
     card_entities: Query<Entity, With<Card>>,
+    transforms: Query<&Transform, With<Card>>,
 ) {
     for entity in &card_entities {
         let result = {
             let mut card = cards.get_mut(entity).unwrap();
-            if let Some(combat_state) = &mut card.combat_state {
+            // stunned cards hold their attack until the stun wears off
+            let stunned = card.effects.is_stunned();
+            if let Some(combat_state) = card.combat_state.as_mut().filter(|_| !stunned) {
                 if combat_state.cooldown.tick(time.delta()).just_finished() {
-                    Some((combat_state.target, card.info.stats.damage))
+                    Some(combat_state.target)
                 } else {
                     None
                 }
@@ -908,19 +1654,42 @@ fn combat(
             }
         };
 
-        if let Some((damaged_entity, damage)) = result {
+        if let Some(damaged_entity) = result {
             if let Ok([mut target_card, mut card]) = cards.get_many_mut([damaged_entity, entity]) {
+                // fighting back is up to the combat zone both cards are in
+                let outcome = card
+                    .combat_stats()
+                    .attack(&target_card.combat_stats(), &mut rng);
                 target_card.info.stats.health =
-                    (target_card.info.stats.health - damage as isize).max(0);
-                if target_card.combat_state.is_none() {
-                    target_card.combat_state = Some(CombatState {
-                        cooldown: Timer::from_seconds(0.9, TimerMode::Repeating),
-                        target: entity,
-                    });
+                    (target_card.info.stats.health - outcome.damage() as isize).max(0);
+                let killed = target_card.info.stats.health == 0;
+                if let (Some(inflicts), AttackOutcome::Hit { .. }, false) =
+                    (card.info.stats.inflicts, outcome, killed)
+                {
+                    if rng.gen::<f32>() < inflicts.chance {
+                        target_card.effects.apply(inflicts.kind);
+                    }
                 }
-                if target_card.info.stats.health == 0 {
+                hits.send(CombatHit {
+                    attacker: entity,
+                    target: damaged_entity,
+                    outcome,
+                    killed,
+                    lunge: true,
+                });
+                if killed {
                     card.combat_state = None;
-                    commands.entity(damaged_entity).despawn_recursive();
+                    let translation = transforms
+                        .get(damaged_entity)
+                        .map_or(Vec3::ZERO, |transform| transform.translation);
+                    kill_card(
+                        &mut commands,
+                        &mut stack_roots,
+                        &mut cards,
+                        &mut deaths,
+                        damaged_entity,
+                        translation,
+                    );
                 }
             } else {
                 cards.get_mut(entity).unwrap().combat_state = None;
@@ -928,3 +1697,189 @@ fn combat(
         }
     }
 }
+
+/// Lunges attackers towards their target and back, and flashes the cards they hit.
+fn animate_attacks(
+    time: Res<Time>,
+    card_data: Res<CardData>,
+    selected: Res<SelectedCard>,
+    mut hits: EventReader<CombatHit>,
+    mut cards: Query<(Entity, &mut Card, &mut Transform, &Children, Has<FaceDown>)>,
+    mut materials: Query<&mut Handle<StandardMaterial>>,
+) {
+    for hit in hits.read() {
+        let target = cards.get(hit.target).map(|(_, _, t, _, _)| t.translation);
+        if let (Ok(target), Ok((_, mut card, transform, _, _)), true) =
+            (target, cards.get_mut(hit.attacker), hit.lunge)
+        {
+            let direction = (target - transform.translation)
+                .truncate()
+                .normalize_or_zero();
+            card.animations.lunge = Some(direction.extend(0.0));
+            card.animations.attack_in.reset();
+            card.animations.attack_out.reset();
+        }
+        if let (Ok((_, mut card, _, _, _)), false) = (
+            cards.get_mut(hit.target),
+            hit.outcome == AttackOutcome::Dodged,
+        ) {
+            card.animations.hit.reset();
+        }
+    }
+
+    for (entity, mut card, mut transform, children, face_down) in &mut cards {
+        let class = card.class();
+        let animations = &mut card.animations;
+        let mut offset = Vec3::ZERO;
+        if let Some(direction) = animations.lunge {
+            let reach = if !animations.attack_in.finished() {
+                animations.attack_in.tick(time.delta())
+            } else {
+                animations.attack_out.tick(time.delta())
+            };
+            if animations.attack_out.finished() {
+                animations.lunge = None;
+            } else {
+                offset = direction * reach;
+            }
+        }
+        // slotted and picked up cards are put in place anew every frame
+        let applied = if card.slotted_in_tile.is_some() || selected.is_selected(entity) {
+            Vec3::ZERO
+        } else {
+            card.animations.lunge_offset
+        };
+        if offset != applied {
+            transform.translation += offset - applied;
+        }
+        card.animations.lunge_offset = offset;
+
+        let hit = card.animations.hit.tick(time.delta()) > 0.0;
+        // a card lying face down keeps showing its back
+        let material = if face_down {
+            card_data.back_material.clone()
+        } else if hit {
+            card_data.hit_material.clone()
+        } else {
+            card_data.class_material(class)
+        };
+        if let Ok(mut handle) = materials.get_mut(children[0]) {
+            if *handle != material {
+                *handle = material;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fighter(damage: usize, armor: usize, dodge: f32, crit_chance: f32) -> CardStats {
+        CardStats {
+            health: 5,
+            max_health: 5,
+            damage,
+            armor,
+            dodge,
+            crit_chance,
+            attack_speed: 1.0,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn buildings_stay_where_they_are_put_down() {
+        let mut building = Card::from(CardType::House);
+        assert!(building.is_player_controlled());
+        building.placed = true;
+        assert!(!building.is_player_controlled());
+        assert!(!building.is_stackable());
+    }
+
+    #[test]
+    fn attacks_replay_from_the_same_seed() {
+        let attacker = fighter(3, 0, 0.0, 0.25);
+        let defender = fighter(1, 1, 0.3, 0.0);
+        let mut rng = GameRng::from_seed(7);
+        let outcomes: Vec<_> = (0..8)
+            .map(|_| attacker.attack(&defender, &mut rng))
+            .collect();
+        let hit = |damage, critical| AttackOutcome::Hit { damage, critical };
+        assert_eq!(
+            outcomes,
+            [
+                hit(5, true),
+                AttackOutcome::Dodged,
+                hit(5, true),
+                AttackOutcome::Dodged,
+                hit(2, false),
+                hit(2, false),
+                hit(2, false),
+                hit(5, true),
+            ]
+        );
+
+        let mut replay = GameRng::from_seed(7);
+        let replayed: Vec<_> = (0..8)
+            .map(|_| attacker.attack(&defender, &mut replay))
+            .collect();
+        assert_eq!(outcomes, replayed);
+    }
+
+    #[test]
+    fn certain_dodge_always_avoids_the_hit() {
+        let attacker = fighter(3, 0, 0.0, 1.0);
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for _ in 0..100 {
+            assert_eq!(
+                attacker.attack(&fighter(1, 0, 1.0, 0.0), &mut rng),
+                AttackOutcome::Dodged
+            );
+            assert_ne!(
+                attacker.attack(&fighter(1, 0, 0.0, 0.0), &mut rng),
+                AttackOutcome::Dodged
+            );
+        }
+    }
+
+    #[test]
+    fn crits_multiply_the_damage() {
+        let defender = fighter(1, 0, 0.0, 0.0);
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut always = fighter(3, 0, 0.0, 1.0);
+        always.crit_multiplier = 1.5;
+        for _ in 0..100 {
+            // 4.5 rounds up
+            assert_eq!(
+                always.attack(&defender, &mut rng),
+                AttackOutcome::Hit {
+                    damage: 5,
+                    critical: true
+                }
+            );
+            assert_eq!(
+                fighter(3, 0, 0.0, 0.0).attack(&defender, &mut rng),
+                AttackOutcome::Hit {
+                    damage: 3,
+                    critical: false
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn armor_leaves_at_least_one_damage() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let attacker = fighter(2, 0, 0.0, 0.0);
+        for armor in [1, 2, 5] {
+            assert_eq!(
+                attacker.attack(&fighter(1, armor, 0.0, 0.0), &mut rng),
+                AttackOutcome::Hit {
+                    damage: 1,
+                    critical: false
+                }
+            );
+        }
+    }
+}
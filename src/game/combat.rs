@@ -0,0 +1,401 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::game::{
+    card::{unstack_card, AttackOutcome, Card, CardClass, CombatState, SelectedCard, StackRoots},
+    enemy::Archetype,
+    label::WorldLabelBundle,
+    pathfinding::step_towards,
+    targeting::{SpatialGrid, Targeting, TargetingStrategy},
+};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CombatHit>()
+            .add_systems(
+                Update,
+                form_fights.after(crate::game::enemy::engage_enemies),
+            )
+            .add_systems(Update, arrange_fights.after(form_fights))
+            .add_systems(
+                Update,
+                spawn_damage_numbers.after(crate::game::card::combat),
+            )
+            .add_systems(Update, float_damage_numbers.after(spawn_damage_numbers));
+    }
+}
+
+/// A fight between the villagers and enemies gathered around `center`. Both sides line up facing
+/// each other and only attack cards of the other side in the same fight.
+#[derive(Component)]
+pub struct CombatZone {
+    pub center: Vec3,
+    pub villagers: Vec<Entity>,
+    pub enemies: Vec<Entity>,
+}
+
+impl CombatZone {
+    /// Cards this close to the center are drawn into the fight.
+    pub const RECRUIT_RADIUS: f32 = 2.5;
+    /// Distance between the two lines.
+    pub const LINE_GAP: f32 = Card::MELEE_REACH;
+    pub const SPACING: f32 = 0.9;
+    /// How far behind their line ranged enemies stand.
+    pub const BACK_ROW: f32 = 1.2;
+    pub const ARRANGE_SPEED: f32 = 4.0;
+
+    pub fn new(center: Vec3) -> Self {
+        Self {
+            center,
+            villagers: Vec::new(),
+            enemies: Vec::new(),
+        }
+    }
+
+    pub fn join(&mut self, entity: Entity, class: CardClass) {
+        match class {
+            CardClass::Villager => self.villagers.push(entity),
+            CardClass::Enemy => self.enemies.push(entity),
+            CardClass::Resource | CardClass::Building => {}
+        }
+    }
+
+    pub fn opponents(&self, class: CardClass) -> &[Entity] {
+        match class {
+            CardClass::Villager => &self.enemies,
+            CardClass::Enemy | CardClass::Resource | CardClass::Building => &self.villagers,
+        }
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.villagers.iter().chain(self.enemies.iter()).copied()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.villagers.is_empty() || self.enemies.is_empty()
+    }
+}
+
+/// Sent for every attack made, including the ones the target dodged.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CombatHit {
+    pub attacker: Entity,
+    pub target: Entity,
+    pub outcome: AttackOutcome,
+    pub killed: bool,
+    /// Whether the attacker lunges at the target, buildings shooting from afar stay put.
+    pub lunge: bool,
+}
+
+/// Rises from a struck card and fades out. The label showing the number follows this entity, so
+/// it outlives the card.
+#[derive(Component)]
+pub struct DamageNumber {
+    pub timer: Timer,
+    pub label: Entity,
+}
+
+impl DamageNumber {
+    pub const LIFETIME: f32 = 0.8;
+    pub const RISE_SPEED: f32 = 0.8;
+}
+
+/// Turns every new engagement into a fight, draws nearby cards into fights, keeps everyone in a
+/// fight attacking somebody of the other side, and ends fights once a side is gone.
+#[allow(clippy::too_many_arguments)]
+pub fn form_fights(
+    mut commands: Commands,
+    selected: Res<SelectedCard>,
+    grid: Res<SpatialGrid>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut zones: Query<(Entity, &mut CombatZone)>,
+    mut cards: Query<&mut Card>,
+    entities: Query<(Entity, &Transform), With<Card>>,
+    targetings: Query<&Targeting>,
+) {
+    // cards picked up by the player walk out of their fight
+    if let SelectedCard::Some(entity) = *selected {
+        if let Ok(mut card) = cards.get_mut(entity) {
+            if card.combat_zone.take().is_some() {
+                card.combat_state = None;
+            }
+        }
+    }
+    // the dead and the picked up leave their fight
+    for (zone_entity, mut zone) in &mut zones {
+        let still_in = |entity: &Entity| {
+            cards
+                .get(*entity)
+                .is_ok_and(|card| card.combat_zone == Some(zone_entity))
+        };
+        zone.villagers.retain(still_in);
+        zone.enemies.retain(still_in);
+    }
+
+    let mut centers: Vec<(Entity, Vec3)> = zones
+        .iter()
+        .map(|(entity, zone)| (entity, zone.center))
+        .collect();
+    let mut new_zones: HashMap<Entity, CombatZone> = HashMap::new();
+    let mut joins: Vec<(Entity, Entity)> = Vec::new();
+    let translation = |entity: Entity| entities.get(entity).ok().map(|(_, t)| t.translation);
+
+    // whoever engaged somebody starts a fight, or joins the one their target is in
+    for (entity, _) in &entities {
+        let Ok(card) = cards.get(entity) else {
+            continue;
+        };
+        let (Some(combat_state), None) = (&card.combat_state, card.combat_zone) else {
+            continue;
+        };
+        let target = combat_state.target();
+        let (Some(position), Some(target_position)) = (translation(entity), translation(target))
+        else {
+            continue;
+        };
+        let zone = cards
+            .get(target)
+            .ok()
+            .and_then(|target| target.combat_zone)
+            .or_else(|| {
+                centers
+                    .iter()
+                    .find(|(_, center)| {
+                        center.truncate().distance(position.truncate())
+                            <= CombatZone::RECRUIT_RADIUS
+                    })
+                    .map(|(zone, _)| *zone)
+            })
+            .unwrap_or_else(|| {
+                let zone = commands.spawn_empty().id();
+                let center = (position + target_position) / 2.0;
+                centers.push((zone, center));
+                new_zones.insert(zone, CombatZone::new(center));
+                zone
+            });
+        cards.get_mut(entity).unwrap().combat_zone = Some(zone);
+        joins.push((zone, entity));
+        // the target is pulled in wherever it stands
+        let target_free = !selected.is_selected(target)
+            && cards
+                .get(target)
+                .is_ok_and(|target| target.combat_zone.is_none() && target.carried_by.is_none());
+        if target_free {
+            unstack_card(&mut commands, &mut stack_roots, &mut cards, target);
+            cards.get_mut(target).unwrap().combat_zone = Some(zone);
+            joins.push((zone, target));
+        }
+    }
+
+    // anybody else close to a fight is drawn in, except thieves minding their own business
+    for (entity, position) in &entities {
+        let Ok(card) = cards.get(entity) else {
+            continue;
+        };
+        let drawn_in = matches!(card.class(), CardClass::Villager | CardClass::Enemy)
+            && card.combat_zone.is_none()
+            && card.carried_by.is_none()
+            && !selected.is_selected(entity)
+            && (Archetype::of(card.card_type()) != Some(Archetype::Thief)
+                || card.combat_state.is_some());
+        if !drawn_in {
+            continue;
+        }
+        let Some((zone, _)) = centers.iter().find(|(_, center)| {
+            center.truncate().distance(position.translation.truncate())
+                <= CombatZone::RECRUIT_RADIUS
+        }) else {
+            continue;
+        };
+        // fighters leave their stack, workers fight from their slot
+        if card.in_stack() {
+            unstack_card(&mut commands, &mut stack_roots, &mut cards, entity);
+        }
+        cards.get_mut(entity).unwrap().combat_zone = Some(*zone);
+        joins.push((*zone, entity));
+    }
+
+    for (zone, entity) in joins {
+        let Ok(class) = cards.get(entity).map(|card| card.class()) else {
+            continue;
+        };
+        if let Some(new_zone) = new_zones.get_mut(&zone) {
+            new_zone.join(entity, class);
+        } else if let Ok((_, mut zone)) = zones.get_mut(zone) {
+            zone.join(entity, class);
+        }
+    }
+
+    // fights with a side gone release everyone left back to the board
+    let mut ended = Vec::new();
+    for (zone_entity, zone) in zones
+        .iter()
+        .chain(new_zones.iter().map(|(entity, zone)| (*entity, zone)))
+    {
+        if !zone.is_over() {
+            continue;
+        }
+        for member in zone.members() {
+            if let Ok(mut card) = cards.get_mut(member) {
+                card.combat_zone = None;
+                card.combat_state = None;
+            }
+        }
+        ended.push(zone_entity);
+    }
+
+    // everyone left in a fight keeps attacking somebody of the other side
+    for (_, zone) in zones
+        .iter()
+        .chain(new_zones.iter().map(|(entity, zone)| (*entity, zone)))
+    {
+        if zone.is_over() {
+            continue;
+        }
+        for member in zone.members() {
+            let Ok(card) = cards.get(member) else {
+                continue;
+            };
+            let opponents = zone.opponents(card.class());
+            if card
+                .combat_state
+                .as_ref()
+                .is_some_and(|state| opponents.contains(&state.target()))
+            {
+                continue;
+            }
+            let strategy = targetings
+                .get(member)
+                .map_or(TargetingStrategy::Nearest, |targeting| targeting.strategy);
+            let position = translation(member).unwrap_or(zone.center);
+            let Some(target) = strategy
+                .pick_among(opponents.iter().filter_map(|e| grid.get(*e)), position)
+                .map(|sighting| sighting.entity)
+            else {
+                continue;
+            };
+            let mut card = cards.get_mut(member).unwrap();
+            let cooldown = card.combat_stats().attack_cooldown();
+            match &mut card.combat_state {
+                Some(combat_state) => combat_state.retarget(target),
+                None => card.combat_state = Some(CombatState::new(target, cooldown)),
+            }
+        }
+    }
+
+    for zone in ended {
+        new_zones.remove(&zone);
+        commands.entity(zone).despawn();
+    }
+    for (entity, zone) in new_zones {
+        commands.entity(entity).insert(zone);
+    }
+}
+
+/// Moves fighters into two lines facing each other, villagers below and enemies above, with
+/// ranged enemies in a row behind. Workers stay in their tile slots.
+pub fn arrange_fights(
+    time: Res<Time>,
+    zones: Query<&CombatZone>,
+    mut cards: Query<(&Card, &mut Transform)>,
+) {
+    let step = CombatZone::ARRANGE_SPEED * time.delta_seconds();
+    for zone in &zones {
+        for (side, direction) in [(&zone.villagers, -1.0), (&zone.enemies, 1.0)] {
+            let (back, front): (Vec<Entity>, Vec<Entity>) = side
+                .iter()
+                .filter(|entity| {
+                    cards
+                        .get(**entity)
+                        .is_ok_and(|(card, _)| card.slotted_in_tile.is_none())
+                })
+                .partition(|entity| {
+                    cards.get(**entity).is_ok_and(|(card, _)| {
+                        Archetype::of(card.card_type()) == Some(Archetype::Ranged)
+                    })
+                });
+            for (row, members) in [(0.0, front), (CombatZone::BACK_ROW, back)] {
+                let width = (members.len() as f32 - 1.0) * CombatZone::SPACING;
+                for (i, entity) in members.into_iter().enumerate() {
+                    let Ok((card, mut transform)) = cards.get_mut(entity) else {
+                        continue;
+                    };
+                    let spot = zone.center
+                        + card.animations.lunge_offset()
+                        + Vec3::new(
+                            i as f32 * CombatZone::SPACING - width / 2.0,
+                            direction * (CombatZone::LINE_GAP / 2.0 + row),
+                            0.0,
+                        );
+                    let offset = (spot - transform.translation).truncate();
+                    if offset.length() <= step {
+                        transform.translation.x = spot.x;
+                        transform.translation.y = spot.y;
+                    } else {
+                        step_towards(&mut transform, spot, step);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut hits: EventReader<CombatHit>,
+    transforms: Query<&Transform, With<Card>>,
+) {
+    for hit in hits.read() {
+        let Ok(transform) = transforms.get(hit.target) else {
+            continue;
+        };
+        let anchor = commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(
+                transform.translation + Vec3::new(0.0, 0.3, 0.0),
+            )))
+            .id();
+        let (value, color) = match hit.outcome {
+            AttackOutcome::Dodged => ("Dodge".to_string(), Color::rgb(0.8, 0.8, 0.8)),
+            AttackOutcome::Hit {
+                damage,
+                critical: true,
+            } => (format!("-{damage}!"), Color::rgb(1.0, 0.6, 0.1)),
+            AttackOutcome::Hit { damage, .. } if hit.killed => {
+                (format!("-{damage}"), Color::rgb(1.0, 0.9, 0.3))
+            }
+            AttackOutcome::Hit { damage, .. } => {
+                (format!("-{damage}"), Color::rgb(1.0, 0.35, 0.35))
+            }
+        };
+        let mut label = WorldLabelBundle::new(anchor, Vec3::ZERO, 26.0, color);
+        label.text.text.sections[0].value = value;
+        let label = commands.spawn(label).id();
+        commands.entity(anchor).insert(DamageNumber {
+            timer: Timer::from_seconds(DamageNumber::LIFETIME, TimerMode::Once),
+            label,
+        });
+    }
+}
+
+fn float_damage_numbers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut numbers: Query<(Entity, &mut DamageNumber, &mut Transform)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (entity, mut number, mut transform) in &mut numbers {
+        number.timer.tick(time.delta());
+        transform.translation.y += DamageNumber::RISE_SPEED * time.delta_seconds();
+        if let Ok(mut text) = texts.get_mut(number.label) {
+            text.sections[0]
+                .style
+                .color
+                .set_a(1.0 - number.timer.fraction());
+        }
+        // the label despawns along with the entity it follows
+        if number.timer.finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
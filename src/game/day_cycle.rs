@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+
+use crate::game::label::{hud_text, set_text};
+
+pub struct DayCyclePlugin;
+
+impl Plugin for DayCyclePlugin {
+    fn build(&self, app: &mut App) {
+        // the world starts out lit for the time of day the game begins at
+        let cycle = DayCycle::default();
+        app.insert_resource(AmbientLight {
+            color: Color::WHITE,
+            brightness: cycle.brightness(),
+        })
+        .insert_resource(ClearColor(cycle.clear_color()))
+        .insert_resource(cycle)
+        .add_event::<EndOfDay>()
+        .add_systems(Startup, spawn_clock)
+        .add_systems(Startup, spawn_night_shade)
+        .add_systems(Update, advance_day)
+        .add_systems(Update, light_world.after(advance_day))
+        .add_systems(Update, show_clock.after(advance_day));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DayPhase {
+    Dawn,
+    Day,
+    Dusk,
+    Night,
+}
+
+impl DayPhase {
+    /// Fraction of the day at which each phase starts, in order.
+    pub const STARTS: [(DayPhase, f32); 4] = [
+        (DayPhase::Dawn, 0.0),
+        (DayPhase::Day, 0.1),
+        (DayPhase::Dusk, 0.6),
+        (DayPhase::Night, 0.7),
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DayPhase::Dawn => "Dawn",
+            DayPhase::Day => "Day",
+            DayPhase::Dusk => "Dusk",
+            DayPhase::Night => "Night",
+        }
+    }
+}
+
+/// How far into the current day the game is. Every day starts at dawn and ends with the night,
+/// when [`EndOfDay`] is sent.
+#[derive(Resource)]
+pub struct DayCycle {
+    pub day: u32,
+    pub elapsed: f32,
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        Self {
+            day: 1,
+            // start in the morning rather than at dawn
+            elapsed: Self::LENGTH * 0.1,
+        }
+    }
+}
+
+impl DayCycle {
+    /// Seconds per day.
+    pub const LENGTH: f32 = 120.0;
+    pub const DAY_BRIGHTNESS: f32 = 0.4;
+    pub const NIGHT_BRIGHTNESS: f32 = 0.1;
+    pub const DAY_CLEAR_COLOR: Color = Color::rgb(0.2, 0.2, 0.2);
+    pub const NIGHT_CLEAR_COLOR: Color = Color::rgb(0.05, 0.06, 0.12);
+    /// Color and opacity of the shade laid over the world at midnight.
+    pub const NIGHT_SHADE: Color = Color::rgba(0.02, 0.03, 0.12, 0.45);
+
+    pub fn fraction(&self) -> f32 {
+        self.elapsed / Self::LENGTH
+    }
+
+    pub fn phase(&self) -> DayPhase {
+        let fraction = self.fraction();
+        DayPhase::STARTS
+            .iter()
+            .rev()
+            .find(|(_, start)| fraction >= *start)
+            .map_or(DayPhase::Dawn, |(phase, _)| *phase)
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.phase() == DayPhase::Night
+    }
+
+    /// Seconds left until the end of the day.
+    pub fn remaining(&self) -> f32 {
+        Self::LENGTH - self.elapsed
+    }
+
+    /// How bright it is, from 0 at night to 1 during the day, fading at dawn and dusk.
+    pub fn daylight(&self) -> f32 {
+        let fraction = self.fraction();
+        let [_, (_, day), (_, dusk), (_, night)] = DayPhase::STARTS;
+        match self.phase() {
+            DayPhase::Dawn => fraction / day,
+            DayPhase::Day => 1.0,
+            DayPhase::Dusk => 1.0 - (fraction - dusk) / (night - dusk),
+            DayPhase::Night => 0.0,
+        }
+    }
+
+    /// Brightness of the ambient light, dimmed at night.
+    pub fn brightness(&self) -> f32 {
+        Self::NIGHT_BRIGHTNESS + (Self::DAY_BRIGHTNESS - Self::NIGHT_BRIGHTNESS) * self.daylight()
+    }
+
+    /// The background color, darker at night.
+    pub fn clear_color(&self) -> Color {
+        let daylight = self.daylight();
+        Self::NIGHT_CLEAR_COLOR * (1.0 - daylight) + Self::DAY_CLEAR_COLOR * daylight
+    }
+
+    /// The time of day as shown on the clock, with dawn at 6:00.
+    pub fn hours_and_minutes(&self) -> (u32, u32) {
+        let minutes = ((self.fraction() * 24.0 + 6.0) * 60.0) as u32 % (24 * 60);
+        (minutes / 60, minutes % 60)
+    }
+}
+
+/// Sent when a night is over, right before the next day starts.
+#[derive(Event)]
+pub struct EndOfDay {
+    /// The day that just ended.
+    pub day: u32,
+}
+
+fn advance_day(
+    time: Res<Time>,
+    mut cycle: ResMut<DayCycle>,
+    mut end_of_day: EventWriter<EndOfDay>,
+) {
+    cycle.elapsed += time.delta_seconds();
+    if cycle.elapsed >= DayCycle::LENGTH {
+        cycle.elapsed -= DayCycle::LENGTH;
+        end_of_day.send(EndOfDay { day: cycle.day });
+        cycle.day += 1;
+    }
+}
+
+/// Darkens the world over the course of the evening. Cards and tiles are unlit, so a shade is
+/// laid over them on top of dimming the ambient light.
+#[derive(Component)]
+pub struct NightShade;
+
+fn spawn_night_shade(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            // below labels and panels
+            z_index: ZIndex::Global(-1),
+            ..default()
+        },
+        NightShade,
+    ));
+}
+
+fn light_world(
+    cycle: Res<DayCycle>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut clear_color: ResMut<ClearColor>,
+    mut shades: Query<&mut BackgroundColor, With<NightShade>>,
+) {
+    let brightness = cycle.brightness();
+    if ambient_light.brightness != brightness {
+        ambient_light.brightness = brightness;
+    }
+    let color = cycle.clear_color();
+    if clear_color.0 != color {
+        clear_color.0 = color;
+    }
+    let daylight = cycle.daylight();
+    let shade = DayCycle::NIGHT_SHADE.with_a(DayCycle::NIGHT_SHADE.a() * (1.0 - daylight));
+    for mut background in &mut shades {
+        if background.0 != shade {
+            background.0 = shade;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Clock;
+
+fn spawn_clock(mut commands: Commands) {
+    commands
+        .spawn(hud_text(
+            18.0,
+            Color::WHITE,
+            Style {
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                ..default()
+            },
+        ))
+        .insert(Clock);
+}
+
+fn show_clock(cycle: Res<DayCycle>, mut clocks: Query<&mut Text, With<Clock>>) {
+    let (hours, minutes) = cycle.hours_and_minutes();
+    let value = format!(
+        "Day {}  {:02}:{:02}  {}",
+        cycle.day,
+        hours,
+        minutes,
+        cycle.phase().name()
+    );
+    for mut text in &mut clocks {
+        set_text(&mut text, &value);
+    }
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::game::{
+    card::{despawn_card, stack_cards, Card, CardBundle, CardType, SelectedCard, StackRoots},
+    equipment::EquipmentSlot,
+    tile::{Tile, TileGrid},
+};
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            sell_cards
+                .after(crate::game::card::move_cards)
+                .before(crate::game::card::evaluate_stacks),
+        );
+    }
+}
+
+/// Whether a stack holding these card types is enough to pay `price` coins.
+pub fn pays(stack: &[CardType], price: usize) -> bool {
+    stack.len() >= price && stack.iter().all(|card_type| *card_type == CardType::Coin)
+}
+
+/// What a card sells for, including the items it carries.
+pub fn sell_price(card: &Card) -> Option<usize> {
+    let items = EquipmentSlot::ALL
+        .iter()
+        .filter_map(|slot| card.equipment.get(*slot))
+        .filter_map(|item| item.sell_value())
+        .sum::<usize>();
+    card.card_type().sell_value().map(|value| value + items)
+}
+
+/// What a card is worth in coins to whoever takes it, coins included.
+pub fn worth(card: &Card) -> usize {
+    if card.card_type() == CardType::Coin {
+        1
+    } else {
+        sell_price(card).unwrap_or(0)
+    }
+}
+
+/// Cards the player drops on a market are sold, the whole stack at once. The coins pile up at
+/// the market's spawn point, where they stack and are left alone since coins can't be sold.
+#[allow(clippy::too_many_arguments)]
+fn sell_cards(
+    mut commands: Commands,
+    selected: Res<SelectedCard>,
+    tile_grid: Res<TileGrid>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut held: Local<Option<Entity>>,
+    mut cards: Query<&mut Card>,
+    transforms: Query<&Transform, With<Card>>,
+    tiles: Query<(&Tile, &Transform), Without<Card>>,
+) {
+    let dropped = match *selected {
+        SelectedCard::Some(entity) => held.replace(entity).filter(|held| *held != entity),
+        SelectedCard::None => held.take(),
+    };
+    let Some((dropped, transform)) =
+        dropped.and_then(|dropped| Some((dropped, transforms.get(dropped).ok()?)))
+    else {
+        return;
+    };
+    let Some((Tile::Market, tile_transform)) = tile_grid
+        .get(&Tile::translation_to_grid(transform.translation))
+        .and_then(|tile| tiles.get(*tile).ok())
+    else {
+        return;
+    };
+
+    let payout = Tile::spawn_point(tile_transform.translation);
+    for entity in stack_cards(&cards.to_readonly(), dropped) {
+        let Some(price) = cards.get(entity).ok().and_then(sell_price) else {
+            continue;
+        };
+        despawn_card(&mut commands, &mut stack_roots, &mut cards, entity);
+        for _ in 0..price {
+            commands.spawn(CardBundle {
+                card: Card::from(CardType::Coin),
+                transform: Transform::from_translation(payout),
+                ..default()
+            });
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use bevy::prelude::*;
+
+use crate::game::{
+    card::{
+        despawn_card, unstack_card, Card, CardBundle, CardClass, CardType, CombatState,
+        SelectedCard, StackRoots,
+    },
+    pathfinding::{step_towards, walk_towards, NavGrid, Path},
+    targeting::{Sighting, SpatialGrid, Targeting, TargetingStrategy},
+};
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, on_spawn_enemy)
+            .add_systems(Update, enrage_bosses)
+            .add_systems(
+                Update,
+                handle_enemies
+                    .after(enrage_bosses)
+                    .after(crate::game::targeting::index_cards)
+                    .after(crate::game::pathfinding::update_nav_grid),
+            )
+            .add_systems(Update, carry_loot.after(handle_enemies))
+            .add_systems(Update, engage_enemies.after(carry_loot));
+    }
+}
+
+/// How an enemy card picks its targets and moves around.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Archetype {
+    /// Walks up to the nearest villager and fights it.
+    Grunt,
+    /// Grabs resource cards and runs back to where it came from.
+    Thief,
+    /// Keeps its distance and attacks from range.
+    Ranged,
+    /// Slow and tough, goes for villagers working on tiles first.
+    Brute,
+    /// Gets faster and calls for help once it is badly hurt.
+    Boss,
+}
+
+impl Archetype {
+    /// Ranged enemies back off from villagers closer than this.
+    pub const KITE_DISTANCE: f32 = 1.8;
+
+    pub fn of(card_type: CardType) -> Option<Self> {
+        match card_type {
+            CardType::Goblin => Some(Archetype::Grunt),
+            CardType::Thief => Some(Archetype::Thief),
+            CardType::Archer => Some(Archetype::Ranged),
+            CardType::Brute => Some(Archetype::Brute),
+            CardType::Warlord => Some(Archetype::Boss),
+            CardType::Villager
+            | CardType::Log
+            | CardType::Berry
+            | CardType::Sword
+            | CardType::Shield
+            | CardType::Axe
+            | CardType::Hoe
+            | CardType::Coin
+            | CardType::Pack(_)
+            | CardType::Grave
+            | CardType::House
+            | CardType::Storage
+            | CardType::Watchtower => None,
+        }
+    }
+
+    pub fn speed(&self) -> f32 {
+        match self {
+            Archetype::Grunt => 1.0,
+            Archetype::Thief => 1.4,
+            Archetype::Ranged => 0.8,
+            Archetype::Brute => 0.6,
+            Archetype::Boss => 0.5,
+        }
+    }
+
+    /// How close it has to be to its target to attack.
+    pub fn reach(&self) -> f32 {
+        match self {
+            Archetype::Ranged => 3.0,
+            _ => Card::MELEE_REACH,
+        }
+    }
+
+    /// How it picks among the cards it is after.
+    pub fn targeting(&self) -> TargetingStrategy {
+        match self {
+            Archetype::Grunt => TargetingStrategy::Nearest,
+            Archetype::Thief | Archetype::Boss => TargetingStrategy::HighestValueStack,
+            Archetype::Ranged => TargetingStrategy::Weakest,
+            Archetype::Brute => TargetingStrategy::SlottedWorker,
+        }
+    }
+
+    /// Whether it is after the given card at all.
+    pub fn preys_on(&self, sighting: &Sighting) -> bool {
+        match self {
+            Archetype::Thief => sighting.stealable,
+            _ => sighting.class == CardClass::Villager,
+        }
+    }
+}
+
+/// Where a thief came from and the card it is running away with.
+#[derive(Component)]
+pub struct Thief {
+    pub home: Vec3,
+    pub grabbing: Option<Entity>,
+    pub loot: Option<Entity>,
+}
+
+impl Thief {
+    pub const GRAB_DISTANCE: f32 = 0.3;
+    pub const LOADED_SPEED: f32 = 0.7;
+}
+
+#[derive(Component, Default)]
+pub struct Boss {
+    pub enraged: bool,
+}
+
+impl Boss {
+    pub const ENRAGED_SPEED: f32 = 1.0;
+    pub const ENRAGED_ATTACK_SPEED: f32 = 1.25;
+    pub const MINIONS: usize = 2;
+}
+
+fn on_spawn_enemy(mut commands: Commands, cards: Query<(Entity, &Card, &Transform), Added<Card>>) {
+    for (entity, card, transform) in &cards {
+        let Some(archetype) = Archetype::of(card.card_type()) else {
+            continue;
+        };
+        commands
+            .entity(entity)
+            .insert((Targeting::new(archetype.targeting()), Path::default()));
+        match archetype {
+            Archetype::Thief => {
+                commands.entity(entity).insert(Thief {
+                    home: transform.translation,
+                    grabbing: None,
+                    loot: None,
+                });
+            }
+            Archetype::Boss => {
+                commands.entity(entity).insert(Boss::default());
+            }
+            _ => {}
+        }
+    }
+}
+
+enum Intent {
+    Attack(Entity, Vec3),
+    Retreat(Vec3),
+    Steal(Entity, Vec3),
+    Flee(Vec3),
+    Idle,
+}
+
+/// Bosses call for help the first time they drop to half health, and fight faster from then on.
+fn enrage_bosses(mut commands: Commands, mut bosses: Query<(&mut Boss, &mut Card, &Transform)>) {
+    for (mut boss, mut card, transform) in &mut bosses {
+        let stats = &card.info.stats;
+        if boss.enraged || stats.health * 2 > stats.max_health as isize {
+            continue;
+        }
+        boss.enraged = true;
+        for i in 0..Boss::MINIONS {
+            let side = if i % 2 == 0 { -1.0 } else { 1.0 };
+            commands.spawn(CardBundle {
+                card: Card::from(CardType::Goblin),
+                transform: Transform::from_xyz(
+                    transform.translation.x + side * Card::SPAWN_OFFSET,
+                    transform.translation.y,
+                    0.0,
+                ),
+                ..default()
+            });
+        }
+        card.info.stats.attack_speed = Boss::ENRAGED_ATTACK_SPEED;
+        let cooldown = card.info.stats.attack_cooldown();
+        if let Some(combat_state) = &mut card.combat_state {
+            combat_state.set_cooldown(cooldown);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_enemies(
+    time: Res<Time>,
+    grid: Res<SpatialGrid>,
+    nav_grid: Res<NavGrid>,
+    mut thieves: Query<&mut Thief>,
+    bosses: Query<&Boss>,
+    mut targetings: Query<&mut Targeting>,
+    mut paths: Query<&mut Path>,
+    mut cards: Query<(Entity, &mut Card, &mut Transform)>,
+) {
+    let mut intents = Vec::new();
+    for (entity, card, transform) in &cards {
+        let Some(archetype) = Archetype::of(card.card_type()) else {
+            continue;
+        };
+        // fighters are moved around by their combat zone
+        if card.combat_zone.is_some() || card.effects.is_stunned() {
+            continue;
+        }
+        let position = transform.translation;
+
+        if let Ok(Thief {
+            home,
+            loot: Some(_),
+            ..
+        }) = thieves.get(entity)
+        {
+            intents.push((entity, archetype, Intent::Flee(*home)));
+            continue;
+        }
+        let Ok(mut targeting) = targetings.get_mut(entity) else {
+            continue;
+        };
+        let target = targeting.update(time.delta(), &grid, position, |s| archetype.preys_on(s));
+        let intent = match (archetype, target) {
+            (_, None) => Intent::Idle,
+            (Archetype::Thief, Some(s)) => Intent::Steal(s.entity, s.translation),
+            (Archetype::Ranged, Some(s)) => {
+                // back off from whichever villager comes too close, not just the target
+                match grid.nearest(position, |s| s.class == CardClass::Villager) {
+                    Some(closest) if closest.distance(position) < Archetype::KITE_DISTANCE => {
+                        Intent::Retreat(closest.translation)
+                    }
+                    _ => Intent::Attack(s.entity, s.translation),
+                }
+            }
+            (_, Some(s)) => Intent::Attack(s.entity, s.translation),
+        };
+        intents.push((entity, archetype, intent));
+    }
+
+    let delta = time.delta_seconds();
+    for (entity, archetype, intent) in intents {
+        let Ok((_, mut card, mut transform)) = cards.get_mut(entity) else {
+            continue;
+        };
+        let Ok(mut path) = paths.get_mut(entity) else {
+            continue;
+        };
+        let mut speed = archetype.speed();
+        let cooldown = card.info.stats.attack_cooldown();
+
+        if bosses.get(entity).is_ok_and(|boss| boss.enraged) {
+            speed = Boss::ENRAGED_SPEED;
+        }
+
+        match intent {
+            Intent::Attack(target, target_translation) => {
+                let distance = target_translation
+                    .truncate()
+                    .distance(transform.translation.truncate());
+                if distance > archetype.reach() {
+                    let step = speed * delta;
+                    walk_towards(
+                        &mut transform,
+                        target_translation,
+                        step,
+                        &nav_grid,
+                        &mut path,
+                    );
+                    card.combat_state = None;
+                } else if card.combat_state.as_ref().map(CombatState::target) != Some(target) {
+                    card.combat_state = Some(CombatState::new(target, cooldown));
+                }
+            }
+            Intent::Retreat(from) => {
+                let away = transform.translation * 2.0 - from;
+                let mut moved = *transform;
+                step_towards(&mut moved, away, speed * delta);
+                if nav_grid.passable(moved.translation) {
+                    *transform = moved;
+                }
+                card.combat_state = None;
+            }
+            Intent::Steal(loot, loot_translation) => {
+                card.combat_state = None;
+                let distance = loot_translation
+                    .truncate()
+                    .distance(transform.translation.truncate());
+                if distance > Thief::GRAB_DISTANCE {
+                    let step = speed * delta;
+                    walk_towards(&mut transform, loot_translation, step, &nav_grid, &mut path);
+                } else if let Ok(mut thief) = thieves.get_mut(entity) {
+                    thief.grabbing = Some(loot);
+                }
+            }
+            Intent::Flee(home) => {
+                card.combat_state = None;
+                let step = Thief::LOADED_SPEED * delta;
+                walk_towards(&mut transform, home, step, &nav_grid, &mut path);
+            }
+            Intent::Idle => {
+                card.combat_state = None;
+            }
+        }
+    }
+}
+
+/// Lets thieves pick up resource cards and drags the loot along with them. A thief that makes
+/// it home disappears together with its loot.
+fn carry_loot(
+    mut commands: Commands,
+    mut stack_roots: ResMut<StackRoots>,
+    mut thieves: Query<(Entity, &mut Thief)>,
+    mut cards: Query<&mut Card>,
+    mut transforms: Query<&mut Transform, With<Card>>,
+) {
+    for (entity, mut thief) in &mut thieves {
+        if let Some(loot) = thief.grabbing.take() {
+            if thief.loot.is_none() && cards.get(loot).is_ok_and(|card| card.carried_by.is_none()) {
+                unstack_card(&mut commands, &mut stack_roots, &mut cards, loot);
+                if let Ok(mut card) = cards.get_mut(loot) {
+                    card.carried_by = Some(entity);
+                    thief.loot = Some(loot);
+                }
+            }
+        }
+
+        let Some(loot) = thief.loot else {
+            continue;
+        };
+        // the player can snatch the loot back by picking it up
+        if !cards
+            .get(loot)
+            .is_ok_and(|card| card.carried_by == Some(entity))
+        {
+            thief.loot = None;
+            continue;
+        }
+        let Ok(position) = transforms
+            .get(entity)
+            .map(|transform| transform.translation)
+        else {
+            continue;
+        };
+        if position.truncate().distance(thief.home.truncate()) <= Thief::GRAB_DISTANCE {
+            despawn_card(&mut commands, &mut stack_roots, &mut cards, loot);
+            despawn_card(&mut commands, &mut stack_roots, &mut cards, entity);
+        } else if let Ok(mut transform) = transforms.get_mut(loot) {
+            transform.translation.x = position.x + 0.2;
+            transform.translation.y = position.y - 0.2;
+        }
+    }
+
+    // loot of thieves that were killed is dropped where it is
+    for mut card in &mut cards {
+        if card
+            .carried_by
+            .is_some_and(|carrier| !thieves.contains(carrier))
+        {
+            card.carried_by = None;
+        }
+    }
+}
+
+/// Villagers start a fight with enemies that come within reach.
+pub fn engage_enemies(
+    selected: Res<SelectedCard>,
+    grid: Res<SpatialGrid>,
+    mut cards: Query<(Entity, &mut Card, &Transform)>,
+) {
+    for (entity, mut card, transform) in &mut cards {
+        if card.class() != CardClass::Villager
+            || selected.is_selected(entity)
+            || card.combat_state.is_some()
+        {
+            continue;
+        }
+        let position = transform.translation;
+        let is_enemy = |s: &Sighting| s.class == CardClass::Enemy;
+        if let Some(enemy) = grid
+            .nearest(position, is_enemy)
+            .filter(|enemy| enemy.distance(position) <= Card::MELEE_REACH)
+        {
+            card.combat_state = Some(CombatState::new(
+                enemy.entity,
+                card.combat_stats().attack_cooldown(),
+            ));
+        }
+    }
+}
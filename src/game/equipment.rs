@@ -0,0 +1,339 @@
+use bevy::prelude::*;
+
+use crate::game::{
+    card::{
+        despawn_card, Card, CardBundle, CardClass, CardData, CardDied, CardStats, CardType,
+        SelectedCard, StackRoots,
+    },
+    loot::{spawn_drops, CardFlight},
+    tile::{Tile, TileBonus, TileSlots},
+    villager::Profession,
+};
+
+pub struct EquipmentPlugin;
+
+impl Plugin for EquipmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EquipmentData>()
+            .add_systems(
+                PostUpdate,
+                on_spawn_card.after(crate::game::card::on_spawn_card),
+            )
+            .add_systems(
+                Update,
+                equip_items
+                    .after(crate::game::card::move_cards)
+                    .before(crate::game::card::evaluate_stacks),
+            )
+            .add_systems(
+                Update,
+                drop_equipment
+                    .after(crate::game::card::combat)
+                    .after(crate::game::status::tick_status_effects),
+            )
+            .add_systems(
+                Update,
+                count_tools
+                    .after(crate::game::tile::clean_tile_slots)
+                    .before(crate::game::tile::evaluate_tiles),
+            )
+            .add_systems(Update, show_equipment_badges.after(equip_items));
+    }
+}
+
+/// Where on a villager an item goes. Equipping an item into a taken slot drops the old one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EquipmentSlot {
+    Weapon,
+    Offhand,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 2] = [EquipmentSlot::Weapon, EquipmentSlot::Offhand];
+
+    pub fn of(card_type: CardType) -> Option<Self> {
+        match card_type {
+            // tools go in the weapon hand too
+            CardType::Sword | CardType::Axe | CardType::Hoe => Some(EquipmentSlot::Weapon),
+            CardType::Shield => Some(EquipmentSlot::Offhand),
+            _ => None,
+        }
+    }
+}
+
+/// What an item adds to the stats of the villager carrying it.
+#[derive(Default)]
+pub struct StatBonus {
+    pub damage: usize,
+    pub armor: usize,
+    pub crit_chance: f32,
+}
+
+impl StatBonus {
+    pub fn of(item: CardType) -> Self {
+        match item {
+            CardType::Sword => StatBonus {
+                damage: 1,
+                crit_chance: 0.05,
+                ..default()
+            },
+            CardType::Shield => StatBonus {
+                armor: 1,
+                ..default()
+            },
+            CardType::Axe => StatBonus {
+                damage: 1,
+                ..default()
+            },
+            _ => StatBonus::default(),
+        }
+    }
+
+    pub fn add_to(&self, stats: &mut CardStats) {
+        stats.damage += self.damage;
+        stats.armor += self.armor;
+        stats.crit_chance += self.crit_chance;
+    }
+}
+
+/// The items a villager has equipped, at most one per slot.
+#[derive(Default)]
+pub struct Equipment(Vec<CardType>);
+
+impl Equipment {
+    /// Extra workers' worth of production the items give when working the given tile, except
+    /// for the tool of the wearer's trade, which the [`Profession::work_bonus`] stands in for.
+    pub fn production_bonus(&self, tile: &Tile, profession: Option<Profession>) -> f32 {
+        self.0
+            .iter()
+            .filter(|item| profession.is_none() || Profession::of_tool(**item) != profession)
+            .map(|item| Self::tool_bonus(*item, tile))
+            .sum()
+    }
+
+    /// Extra workers' worth of production a single tool gives on the given tile, whether it is
+    /// carried by a worker or slotted into the tile.
+    pub fn tool_bonus(item: CardType, tile: &Tile) -> f32 {
+        match (item, tile) {
+            (CardType::Axe, Tile::Woods { .. } | Tile::LumberCamp { .. }) => 0.5,
+            _ => 0.0,
+        }
+    }
+
+    pub fn get(&self, slot: EquipmentSlot) -> Option<CardType> {
+        self.0
+            .iter()
+            .find(|item| EquipmentSlot::of(**item) == Some(slot))
+            .copied()
+    }
+
+    /// Adds what the items give to the stats, except for the tool of the wearer's trade, which
+    /// the [`Profession::combat_bonus`] stands in for.
+    pub fn add_to(&self, stats: &mut CardStats, profession: Option<Profession>) {
+        for item in &self.0 {
+            if profession.is_none() || Profession::of_tool(*item) != profession {
+                StatBonus::of(*item).add_to(stats);
+            }
+        }
+    }
+
+    /// Puts the item on and returns the one it replaced, if any.
+    pub fn equip(&mut self, item: CardType) -> Option<CardType> {
+        let slot = EquipmentSlot::of(item)?;
+        let replaced = self.get(slot);
+        if let Some(replaced) = replaced {
+            self.0.retain(|equipped| *equipped != replaced);
+        }
+        self.0.push(item);
+        replaced
+    }
+
+    /// Takes off all items.
+    pub fn take(&mut self) -> Vec<CardType> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+/// Shows the item a villager carries in one of its slots.
+#[derive(Component)]
+pub struct EquipmentBadge {
+    pub slot: EquipmentSlot,
+    pub icon: Entity,
+}
+
+#[derive(Resource)]
+pub struct EquipmentData {
+    badge_mesh: Handle<Mesh>,
+    icon_mesh: Handle<Mesh>,
+    badge_material: Handle<StandardMaterial>,
+}
+
+impl EquipmentData {
+    pub const BADGE_RADIUS: f32 = 0.08;
+    pub const ICON_SIZE: f32 = 0.13;
+    /// Bottom right corner of the portrait, the next slot goes to its left.
+    pub const ORIGIN: Vec3 = Vec3::new(0.28, -0.32, 0.01);
+    pub const SPACING: f32 = 0.18;
+}
+
+impl FromWorld for EquipmentData {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self {
+            badge_mesh: meshes.add(Circle::new(Self::BADGE_RADIUS)),
+            icon_mesh: meshes.add(Rectangle {
+                half_size: Vec2::splat(Self::ICON_SIZE / 2.0),
+            }),
+            badge_material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.25, 0.22, 0.2),
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                depth_bias: 0.1,
+                ..default()
+            }),
+        }
+    }
+}
+
+fn on_spawn_card(
+    mut commands: Commands,
+    equipment_data: Res<EquipmentData>,
+    cards: Query<(Entity, &Card), Added<Card>>,
+) {
+    for (entity, card) in &cards {
+        if card.class() != CardClass::Villager {
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            for (i, slot) in EquipmentSlot::ALL.into_iter().enumerate() {
+                let mut icon = None;
+                parent
+                    .spawn(PbrBundle {
+                        material: equipment_data.badge_material.clone(),
+                        mesh: equipment_data.badge_mesh.clone(),
+                        transform: Transform::from_translation(
+                            EquipmentData::ORIGIN - Vec3::X * i as f32 * EquipmentData::SPACING,
+                        ),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        icon = Some(
+                            parent
+                                .spawn(PbrBundle {
+                                    mesh: equipment_data.icon_mesh.clone(),
+                                    transform: Transform::from_xyz(0.0, 0.0, 0.001),
+                                    ..default()
+                                })
+                                .id(),
+                        );
+                    })
+                    .insert(EquipmentBadge {
+                        slot,
+                        icon: icon.unwrap(),
+                    });
+            }
+        });
+    }
+}
+
+/// Items dropped on top of a villager are equipped instead of forming a stack.
+fn equip_items(
+    mut commands: Commands,
+    selected: Res<SelectedCard>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<&mut Card>,
+    card_entities: Query<Entity, With<Card>>,
+    transforms: Query<&Transform>,
+) {
+    for entity in &card_entities {
+        let Ok(card) = cards.get(entity) else {
+            continue;
+        };
+        let item = card.card_type();
+        let (Some(_), Some(wearer)) = (EquipmentSlot::of(item), card.stack_parent) else {
+            continue;
+        };
+        if selected.is_selected(wearer) {
+            continue;
+        }
+        let Ok(mut wearer_card) = cards.get_mut(wearer) else {
+            continue;
+        };
+        if wearer_card.class() != CardClass::Villager {
+            continue;
+        }
+        let replaced = wearer_card.equipment.equip(item);
+        // picking up a tool is taking up its trade
+        if let (Some(identity), Some(profession)) =
+            (wearer_card.identity.as_mut(), Profession::of_tool(item))
+        {
+            identity.profession = Some(profession);
+        }
+        despawn_card(&mut commands, &mut stack_roots, &mut cards, entity);
+        if let (Some(replaced), Ok(transform)) = (replaced, transforms.get(wearer)) {
+            commands.spawn(CardBundle {
+                card: Card::from(replaced),
+                transform: Transform::from_xyz(
+                    transform.translation.x + Card::SPAWN_OFFSET,
+                    transform.translation.y,
+                    0.0,
+                ),
+                ..default()
+            });
+        }
+    }
+}
+
+/// Villagers drop the items they carried where they died.
+fn drop_equipment(mut commands: Commands, mut deaths: EventReader<CardDied>) {
+    for death in deaths.read() {
+        let landing = death.translation + Vec3::new(0.0, CardFlight::SCATTER, 0.0);
+        spawn_drops(&mut commands, &death.equipment, death.translation, landing);
+    }
+}
+
+fn count_tools(mut tiles: Query<(&Tile, &TileSlots, &mut TileBonus)>, cards: Query<&Card>) {
+    for (tile, tile_slots, mut bonus) in &mut tiles {
+        let tools = tile_slots
+            .cards()
+            .filter_map(|entity| cards.get(entity).ok())
+            .map(|card| {
+                let profession = card.identity.as_ref().and_then(|i| i.profession);
+                card.equipment.production_bonus(tile, profession)
+                    + Equipment::tool_bonus(card.card_type(), tile)
+            })
+            .sum::<f32>();
+        if bonus.tools != tools {
+            bonus.tools = tools;
+        }
+    }
+}
+
+fn show_equipment_badges(
+    card_data: Res<CardData>,
+    cards: Query<(&Card, &Children)>,
+    mut badges: Query<(&EquipmentBadge, &mut Visibility)>,
+    mut materials: Query<&mut Handle<StandardMaterial>>,
+) {
+    for (card, children) in &cards {
+        for child in children {
+            let Ok((badge, mut visibility)) = badges.get_mut(*child) else {
+                continue;
+            };
+            let Some(item) = card.equipment.get(badge.slot) else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+            *visibility = Visibility::Inherited;
+            let material = card_data.portrait_material(item);
+            if let Ok(mut handle) = materials.get_mut(badge.icon) {
+                if *handle != material {
+                    *handle = material;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::game::{
+    card::{Card, CardClass},
+    progress_bar::ProgressBar,
+    rng::GameRng,
+    tile::{
+        toggle_progress_bar, SlotFilter, Tile, TileBundle, TileData, TileGrid, TileGridLocation,
+        TileSlots,
+    },
+};
+
+pub struct ExplorationPlugin;
+
+impl Plugin for ExplorationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FogGrid>()
+            .add_systems(PostUpdate, on_spawn_fog)
+            .add_systems(Update, spawn_fog)
+            .add_systems(Update, explore_fog.after(crate::game::tile::hover_tile));
+    }
+}
+
+/// An unexplored tile next to the known grid. Its real type stays hidden until villagers
+/// have spent [`FogTile::EXPLORE_TIME`] slotted in it, or until it is bought with
+/// [`FogTile::PRICE`] coins.
+#[derive(Component, Clone, Copy)]
+pub struct FogTile {
+    pub hidden: Tile,
+    pub progress_bar: Option<Entity>,
+    pub bought: bool,
+}
+
+impl FogTile {
+    pub const EXPLORE_TIME: f32 = 10.0;
+    pub const PRICE: usize = 5;
+    pub const SLOTS: &'static [(SlotFilter, Vec2)] =
+        &[(SlotFilter::Class(CardClass::Villager), Vec2::ZERO)];
+    pub const HIDDEN_TILES: &'static [(Tile, u32)] = &[
+        (Tile::Woods { progress_bar: None }, 3),
+        (Tile::Farm { progress_bar: None }, 2),
+        (Tile::Lake, 1),
+        (Tile::Enemies { progress_bar: None }, 1),
+    ];
+
+    pub fn new(hidden: Tile) -> Self {
+        Self {
+            hidden,
+            progress_bar: None,
+            bought: false,
+        }
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct FogBundle {
+    pub fog: FogTile,
+    pub tile_grid_location: TileGridLocation,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibiltiy: InheritedVisibility,
+}
+
+impl Default for FogTile {
+    fn default() -> Self {
+        Self::new(Tile::default())
+    }
+}
+
+/// Unexplored locations, kept apart from [`TileGrid`] until they are revealed.
+#[derive(Default, Deref, DerefMut, Resource)]
+pub struct FogGrid(HashMap<IVec2, Entity>);
+
+fn spawn_fog(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    tile_grid: Res<TileGrid>,
+    fog_grid: Res<FogGrid>,
+) {
+    if !tile_grid.is_changed() {
+        return;
+    }
+
+    let mut queued = HashSet::new();
+    for location in tile_grid.keys() {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                let neighbour = *location + IVec2::new(x, y);
+                if tile_grid.contains_key(&neighbour)
+                    || fog_grid.contains_key(&neighbour)
+                    || !queued.insert(neighbour)
+                {
+                    continue;
+                }
+                let Some(hidden) = rng.weighted(FogTile::HIDDEN_TILES) else {
+                    continue;
+                };
+                commands.spawn(FogBundle {
+                    fog: FogTile::new(hidden),
+                    tile_grid_location: TileGridLocation(neighbour),
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+fn on_spawn_fog(
+    mut commands: Commands,
+    tile_data: Res<TileData>,
+    mut fog_grid: ResMut<FogGrid>,
+    mut fogs: Query<(Entity, &TileGridLocation, &mut Transform), Added<FogTile>>,
+) {
+    for (entity, location, mut transform) in &mut fogs {
+        fog_grid.insert(location.0, entity);
+        transform.translation = Tile::grid_to_translation(location.0);
+        let mut tile_slots = TileSlots::default();
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn(PbrBundle {
+                material: tile_data.fog_material.clone(),
+                mesh: tile_data.mesh.clone(),
+                ..default()
+            });
+            tile_slots = TileSlots::spawn(parent, &tile_data, FogTile::SLOTS);
+        });
+        commands.entity(entity).insert(tile_slots);
+    }
+}
+
+fn explore_fog(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut fog_grid: ResMut<FogGrid>,
+    mut fogs: Query<(Entity, &mut FogTile, &TileSlots, &TileGridLocation)>,
+    mut progress_bars: Query<&mut ProgressBar>,
+    mut cards: Query<&mut Card>,
+) {
+    for (entity, mut fog, tile_slots, location) in &mut fogs {
+        let explorers = tile_slots.filled(SlotFilter::Class(CardClass::Villager));
+        toggle_progress_bar(
+            &mut commands,
+            entity,
+            &mut fog.progress_bar,
+            explorers > 0,
+            FogTile::EXPLORE_TIME,
+        );
+        let mut explored = fog.bought;
+        if let Some(bar_entity) = fog.progress_bar {
+            if let Ok(mut bar) = progress_bars.get_mut(bar_entity) {
+                bar.add(time.delta_seconds() * explorers as f32);
+                explored |= bar.finished();
+            }
+        }
+        if explored {
+            // release the explorers, they stay on top of the newly revealed tile
+            for card_entity in tile_slots.cards() {
+                if let Ok(mut card) = cards.get_mut(card_entity) {
+                    card.slotted_in_tile = None;
+                }
+            }
+            fog_grid.remove(&location.0);
+            commands.entity(entity).despawn_recursive();
+            commands.spawn(TileBundle {
+                tile: fog.hidden,
+                tile_grid_location: *location,
+                ..default()
+            });
+        }
+    }
+}
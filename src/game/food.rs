@@ -0,0 +1,255 @@
+use bevy::prelude::*;
+
+use crate::game::{
+    card::{
+        despawn_card, kill_card, unstack_card, Card, CardClass, CardDied, SelectedCard, StackRoots,
+    },
+    day_cycle::{DayCycle, EndOfDay},
+    label::{hud_text, set_text},
+    loot::CardFlight,
+};
+
+pub struct FoodPlugin;
+
+impl Plugin for FoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Feeding>()
+            .add_systems(Startup, spawn_feeding_panel)
+            .add_systems(
+                Update,
+                feed_villagers
+                    .after(crate::game::card::combat)
+                    .after(crate::game::status::tick_status_effects),
+            )
+            .add_systems(
+                Update,
+                finish_meals
+                    .after(feed_villagers)
+                    .after(crate::game::loot::fly_cards),
+            )
+            .add_systems(Update, show_feeding.after(feed_villagers));
+    }
+}
+
+/// Every villager eats or starves at the end of the day.
+#[derive(Resource, Default)]
+pub struct Feeding {
+    /// Villagers that went hungry at the last feeding.
+    pub starved: usize,
+}
+
+impl Feeding {
+    /// How much food every villager eats at a feeding.
+    pub const FOOD_PER_VILLAGER: usize = 1;
+    /// Health lost by villagers that find nothing to eat.
+    pub const STARVATION_DAMAGE: isize = 1;
+}
+
+/// A food card on its way to the villager eating it.
+#[derive(Component)]
+pub struct Meal {
+    pub eater: Entity,
+}
+
+/// Every villager in turn eats the food lying closest to it. Whoever finds nothing left loses
+/// health. A villager held by the player skips the meal.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn feed_villagers(
+    mut commands: Commands,
+    mut end_of_day: EventReader<EndOfDay>,
+    selected: Res<SelectedCard>,
+    mut feeding: ResMut<Feeding>,
+    mut deaths: EventWriter<CardDied>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<&mut Card>,
+    positioned: Query<(Entity, &Transform), (With<Card>, Without<CardFlight>)>,
+) {
+    if end_of_day.read().count() == 0 {
+        return;
+    }
+
+    let mut villagers = Vec::new();
+    let mut food = Vec::new();
+    for (entity, transform) in &positioned {
+        let Ok(card) = cards.get(entity) else {
+            continue;
+        };
+        if card.class() == CardClass::Villager
+            && card.info.stats.health > 0
+            && !selected.is_selected(entity)
+        {
+            villagers.push((entity, transform.translation));
+        } else if let Some(value) = card.card_type().food_value() {
+            if card.slotted_in_tile.is_none()
+                && card.carried_by.is_none()
+                && !selected.is_selected(entity)
+            {
+                food.push((entity, transform.translation, value));
+            }
+        }
+    }
+
+    feeding.starved = 0;
+    for (eater, position) in villagers {
+        let mut eaten = 0;
+        while eaten < Feeding::FOOD_PER_VILLAGER && !food.is_empty() {
+            let (i, _) = food
+                .iter()
+                .enumerate()
+                .min_by(|(_, a), (_, b)| {
+                    a.1.distance_squared(position)
+                        .total_cmp(&b.1.distance_squared(position))
+                })
+                .unwrap();
+            let (meal, from, value) = food.swap_remove(i);
+            unstack_card(&mut commands, &mut stack_roots, &mut cards, meal);
+            commands
+                .entity(meal)
+                .insert((CardFlight::new(from, position), Meal { eater }));
+            eaten += value;
+        }
+        if eaten >= Feeding::FOOD_PER_VILLAGER {
+            continue;
+        }
+
+        feeding.starved += 1;
+        let Ok(mut card) = cards.get_mut(eater) else {
+            continue;
+        };
+        let stats = &mut card.info.stats;
+        stats.health = (stats.health - Feeding::STARVATION_DAMAGE).max(0);
+        if stats.health == 0 {
+            kill_card(
+                &mut commands,
+                &mut stack_roots,
+                &mut cards,
+                &mut deaths,
+                eater,
+                position,
+            );
+        }
+    }
+}
+
+/// Food is eaten once it reaches the villager.
+fn finish_meals(
+    mut commands: Commands,
+    selected: Res<SelectedCard>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<&mut Card>,
+    meals: Query<Entity, (With<Meal>, Without<CardFlight>)>,
+) {
+    for meal in &meals {
+        if selected.is_selected(meal) {
+            continue;
+        }
+        despawn_card(&mut commands, &mut stack_roots, &mut cards, meal);
+    }
+}
+
+#[derive(Component)]
+pub struct FeedingPanel;
+
+fn spawn_feeding_panel(mut commands: Commands) {
+    commands
+        .spawn(hud_text(
+            18.0,
+            Color::rgb(1.0, 0.9, 0.6),
+            Style {
+                left: Val::Px(10.0),
+                top: Val::Px(10.0),
+                ..default()
+            },
+        ))
+        .insert(FeedingPanel);
+}
+
+fn show_feeding(
+    cycle: Res<DayCycle>,
+    feeding: Res<Feeding>,
+    cards: Query<&Card>,
+    mut panels: Query<&mut Text, With<FeedingPanel>>,
+) {
+    let mut villagers = 0;
+    let mut food = 0;
+    for card in &cards {
+        if card.class() == CardClass::Villager {
+            villagers += 1;
+        } else if let Some(value) = card.card_type().food_value() {
+            food += value;
+        }
+    }
+    let mut value = format!(
+        "Feeding in {:.0}s: {} food, {} needed",
+        cycle.remaining().ceil(),
+        food,
+        villagers * Feeding::FOOD_PER_VILLAGER
+    );
+    if feeding.starved > 0 {
+        value.push_str(&format!("\n{} went hungry last time", feeding.starved));
+    }
+    for mut text in &mut panels {
+        set_text(&mut text, &value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        card::CardType,
+        status::{eat_food, StatusKind},
+    };
+
+    /// Spawns a villager with a berry stacked on it, and returns both.
+    fn villager_with_berry(app: &mut App, health: isize) -> (Entity, Entity) {
+        let mut villager = Card::from(CardType::Villager);
+        villager.info.stats.health = health;
+        let villager = app.world.spawn((villager, Transform::default())).id();
+        let mut berry = Card::from(CardType::Berry);
+        berry.stack_parent = Some(villager);
+        let berry = app.world.spawn((berry, Transform::default())).id();
+        app.world.get_mut::<Card>(villager).unwrap().stack_child = Some(berry);
+        (villager, berry)
+    }
+
+    fn feeding_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<StackRoots>()
+            .init_resource::<SelectedCard>()
+            .init_resource::<Feeding>()
+            .add_event::<CardDied>()
+            .add_event::<EndOfDay>()
+            .add_systems(Update, (eat_food, feed_villagers.after(eat_food)));
+        app
+    }
+
+    #[test]
+    fn food_on_a_healthy_villager_is_kept_for_the_feeding() {
+        let mut app = feeding_app();
+        let max_health = Card::from(CardType::Villager).info.stats.max_health as isize;
+        let (villager, berry) = villager_with_berry(&mut app, max_health);
+
+        app.update();
+        assert!(app.world.get_entity(berry).is_some());
+        let card = app.world.get::<Card>(villager).unwrap();
+        assert!(card.effects.get(StatusKind::Regeneration).is_none());
+
+        app.world.send_event(EndOfDay { day: 1 });
+        app.update();
+        assert_eq!(app.world.resource::<Feeding>().starved, 0);
+        assert_eq!(app.world.get::<Meal>(berry).unwrap().eater, villager);
+    }
+
+    #[test]
+    fn wounded_villagers_eat_the_food_stacked_on_them() {
+        let mut app = feeding_app();
+        let max_health = Card::from(CardType::Villager).info.stats.max_health as isize;
+        let (villager, berry) = villager_with_berry(&mut app, max_health - 1);
+
+        app.update();
+        assert!(app.world.get_entity(berry).is_none());
+        let card = app.world.get::<Card>(villager).unwrap();
+        assert!(card.effects.get(StatusKind::Regeneration).is_some());
+    }
+}
@@ -0,0 +1,97 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use crate::game::camera::PlayerCamera;
+
+pub struct LabelPlugin;
+
+impl Plugin for LabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            position_world_labels.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// A UI text that follows an entity in the world. It is despawned together with its target.
+#[derive(Component)]
+pub struct WorldLabel {
+    pub target: Entity,
+    pub offset: Vec3,
+}
+
+#[derive(Bundle)]
+pub struct WorldLabelBundle {
+    pub label: WorldLabel,
+    pub text: TextBundle,
+}
+
+impl WorldLabelBundle {
+    pub fn new(target: Entity, offset: Vec3, font_size: f32, color: Color) -> Self {
+        Self {
+            label: WorldLabel { target, offset },
+            text: hud_text(font_size, color, Style::default()),
+        }
+    }
+}
+
+/// An empty single section UI text, placed on the screen by `style` and filled in with
+/// [`set_text`].
+pub fn hud_text(font_size: f32, color: Color, style: Style) -> TextBundle {
+    TextBundle::from_section(
+        "",
+        TextStyle {
+            font_size,
+            color,
+            ..default()
+        },
+    )
+    .with_style(Style {
+        position_type: PositionType::Absolute,
+        ..style
+    })
+}
+
+/// Shows `value` in a single section text. It is only written when it changes, so the text isn't
+/// laid out again every frame.
+pub fn set_text(text: &mut Mut<Text>, value: &str) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value.to_string();
+    }
+}
+
+/// Colors a single section text, only writing the color when it changes.
+pub fn set_text_color(text: &mut Mut<Text>, color: Color) {
+    if text.sections[0].style.color != color {
+        text.sections[0].style.color = color;
+    }
+}
+
+fn position_world_labels(
+    mut commands: Commands,
+    cameras: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+    targets: Query<(&GlobalTransform, &InheritedVisibility)>,
+    mut labels: Query<(Entity, &WorldLabel, &Node, &mut Style, &mut Visibility)>,
+) {
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+    for (entity, label, node, mut style, mut visibility) in &mut labels {
+        if let Ok((target_transform, target_visibility)) = targets.get(label.target) {
+            let position = target_transform.translation() + label.offset;
+            if let (Some(point), true) = (
+                camera.world_to_viewport(camera_transform, position),
+                target_visibility.get(),
+            ) {
+                let size = node.size();
+                style.left = Val::Px(point.x - size.x / 2.0);
+                style.top = Val::Px(point.y - size.y / 2.0);
+                *visibility = Visibility::Inherited;
+            } else {
+                *visibility = Visibility::Hidden;
+            }
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
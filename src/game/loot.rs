@@ -0,0 +1,278 @@
+use std::{
+    f32::consts::{PI, TAU},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::game::{
+    animate::{AnimateRange, Ease},
+    card::{Card, CardBundle, CardDied, CardType},
+    rng::GameRng,
+};
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            drop_loot
+                .after(crate::game::card::combat)
+                .after(crate::game::status::tick_status_effects),
+        )
+        .add_systems(Update, fly_cards.after(crate::game::card::move_cards));
+    }
+}
+
+/// The cards handed out by a dead card, a tile or a pack.
+#[derive(Clone, Copy, Debug)]
+pub struct DropTable {
+    /// Always dropped.
+    pub guaranteed: &'static [CardType],
+    /// How many times an entry is picked from `weighted`.
+    pub rolls: usize,
+    /// Entries picked with a probability proportional to their weight, `None` drops nothing.
+    pub weighted: &'static [(Option<CardType>, u32)],
+    /// Dropped on their own, each with the given chance.
+    pub rare: &'static [(CardType, f32)],
+}
+
+impl DropTable {
+    pub const EMPTY: DropTable = DropTable {
+        guaranteed: &[],
+        rolls: 0,
+        weighted: &[],
+        rare: &[],
+    };
+
+    /// What a card drops when it dies.
+    pub fn of(card_type: CardType) -> DropTable {
+        match card_type {
+            CardType::Goblin => DropTable {
+                rolls: 1,
+                weighted: &[
+                    (None, 6),
+                    (Some(CardType::Log), 2),
+                    (Some(CardType::Berry), 2),
+                ],
+                rare: &[(CardType::Sword, 0.03)],
+                ..Self::EMPTY
+            },
+            CardType::Thief => DropTable {
+                rolls: 2,
+                weighted: &[
+                    (None, 2),
+                    (Some(CardType::Log), 1),
+                    (Some(CardType::Berry), 1),
+                ],
+                ..Self::EMPTY
+            },
+            CardType::Archer => DropTable {
+                rolls: 1,
+                weighted: &[(None, 1), (Some(CardType::Log), 1)],
+                rare: &[(CardType::Sword, 0.05)],
+                ..Self::EMPTY
+            },
+            CardType::Brute => DropTable {
+                guaranteed: &[CardType::Log],
+                rare: &[(CardType::Shield, 0.1), (CardType::Axe, 0.05)],
+                ..Self::EMPTY
+            },
+            CardType::Warlord => DropTable {
+                guaranteed: &[CardType::Sword],
+                rolls: 3,
+                weighted: &[(Some(CardType::Log), 1), (Some(CardType::Berry), 1)],
+                rare: &[(CardType::Shield, 0.25)],
+            },
+            // villagers are laid to rest where they fell
+            CardType::Villager => DropTable {
+                guaranteed: &[CardType::Grave],
+                ..Self::EMPTY
+            },
+            CardType::Log
+            | CardType::Berry
+            | CardType::Sword
+            | CardType::Shield
+            | CardType::Axe
+            | CardType::Hoe
+            | CardType::Coin
+            | CardType::Pack(_)
+            | CardType::Grave
+            | CardType::House
+            | CardType::Storage
+            | CardType::Watchtower => Self::EMPTY,
+        }
+    }
+
+    pub fn roll(&self, rng: &mut GameRng) -> Vec<CardType> {
+        let mut drops = self.guaranteed.to_vec();
+        if !self.weighted.is_empty() {
+            drops.extend((0..self.rolls).filter_map(|_| rng.weighted(self.weighted).flatten()));
+        }
+        for (card_type, chance) in self.rare {
+            if rng.gen::<f32>() < *chance {
+                drops.push(*card_type);
+            }
+        }
+        drops
+    }
+}
+
+/// A card on its way from where it dropped to where it lands.
+#[derive(Component)]
+pub struct CardFlight {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub progress: AnimateRange,
+}
+
+impl CardFlight {
+    pub const DURATION: f32 = 0.4;
+    pub const HEIGHT: f32 = 0.6;
+    /// How far from where they dropped the cards land.
+    pub const SCATTER: f32 = 0.9;
+
+    pub fn new(from: Vec3, to: Vec3) -> Self {
+        Self {
+            from,
+            to,
+            progress: AnimateRange::new(
+                Duration::from_secs_f32(Self::DURATION),
+                Ease::Linear,
+                0.0..1.0,
+                false,
+            ),
+        }
+    }
+}
+
+/// Spawns the cards at `origin` and sends them flying to spots spread around `landing`.
+pub fn spawn_drops(
+    commands: &mut Commands,
+    drops: &[CardType],
+    origin: Vec3,
+    landing: Vec3,
+) -> Vec<Entity> {
+    let mut spawned = Vec::new();
+    for (i, card_type) in drops.iter().enumerate() {
+        let angle = i as f32 / drops.len() as f32 * TAU;
+        let spread = if drops.len() > 1 {
+            CardFlight::SCATTER
+        } else {
+            0.0
+        };
+        let to = landing + Vec2::from_angle(angle).extend(0.0) * spread;
+        let entity = commands
+            .spawn((
+                CardBundle {
+                    card: Card::from(*card_type),
+                    transform: Transform::from_xyz(origin.x, origin.y, 0.0),
+                    ..default()
+                },
+                CardFlight::new(origin, to),
+            ))
+            .id();
+        spawned.push(entity);
+    }
+    spawned
+}
+
+fn drop_loot(mut commands: Commands, mut rng: ResMut<GameRng>, mut deaths: EventReader<CardDied>) {
+    for death in deaths.read() {
+        let drops = DropTable::of(death.card_type).roll(&mut rng);
+        let landing = death.translation + Vec3::new(0.0, -CardFlight::SCATTER, 0.0);
+        spawn_drops(&mut commands, &drops, death.translation, landing);
+    }
+}
+
+pub fn fly_cards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cards: Query<(Entity, &mut CardFlight, &mut Transform)>,
+) {
+    for (entity, mut flight, mut transform) in &mut cards {
+        let t = flight.progress.tick(time.delta());
+        let position = flight.from.lerp(flight.to, t);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        transform.translation.z += (t * PI).sin() * CardFlight::HEIGHT;
+        if flight.progress.finished() {
+            commands.entity(entity).remove::<CardFlight>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guaranteed_drops_always_come() {
+        let table = DropTable {
+            guaranteed: &[CardType::Log, CardType::Sword],
+            rolls: 2,
+            weighted: &[(None, 1), (Some(CardType::Berry), 1)],
+            rare: &[(CardType::Shield, 0.5)],
+        };
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for _ in 0..100 {
+            let drops = table.roll(&mut rng);
+            assert!(
+                drops.starts_with(&[CardType::Log, CardType::Sword]),
+                "{drops:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn empty_entries_drop_nothing() {
+        let table = DropTable {
+            rolls: 5,
+            weighted: &[(None, 1)],
+            ..DropTable::EMPTY
+        };
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng), []);
+        }
+    }
+
+    #[test]
+    fn no_rolls_pick_nothing_from_the_weighted_entries() {
+        let table = DropTable {
+            rolls: 0,
+            weighted: &[(Some(CardType::Log), 1)],
+            ..DropTable::EMPTY
+        };
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng), []);
+        }
+    }
+
+    #[test]
+    fn rare_drops_come_with_their_chance() {
+        let table = DropTable {
+            rare: &[(CardType::Sword, 1.0), (CardType::Shield, 0.0)],
+            ..DropTable::EMPTY
+        };
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for _ in 0..100 {
+            assert_eq!(table.roll(&mut rng), [CardType::Sword]);
+        }
+    }
+
+    #[test]
+    fn drops_replay_from_the_same_seed() {
+        let table = DropTable::of(CardType::Warlord);
+        let mut rng = GameRng::from_seed(7);
+        let drops: Vec<_> = (0..20).map(|_| table.roll(&mut rng)).collect();
+        assert!(drops.iter().any(|roll| *roll != drops[0]));
+
+        let mut replay = GameRng::from_seed(7);
+        let replayed: Vec<_> = (0..20).map(|_| table.roll(&mut replay)).collect();
+        assert_eq!(drops, replayed);
+    }
+}
@@ -1,16 +1,50 @@
 pub mod animate;
+pub mod buildings;
 pub mod camera;
 pub mod card;
+pub mod combat;
+pub mod day_cycle;
+pub mod economy;
+pub mod enemy;
+pub mod equipment;
+pub mod exploration;
+pub mod food;
+pub mod label;
+pub mod loot;
+pub mod pack;
+pub mod pathfinding;
 pub mod progress_bar;
+pub mod rng;
+pub mod status;
+pub mod targeting;
 pub mod tile;
+pub mod villager;
+pub mod wave;
 
 use std::f32::consts::PI;
 
 use self::{camera::PlayerCameraPlugin, card::CardInfo};
 use crate::game::{
+    buildings::BuildingPlugin,
     card::{Card, CardBundle, CardPlugin, CardType},
+    combat::CombatPlugin,
+    day_cycle::DayCyclePlugin,
+    economy::EconomyPlugin,
+    enemy::EnemyPlugin,
+    equipment::EquipmentPlugin,
+    exploration::ExplorationPlugin,
+    food::FoodPlugin,
+    label::LabelPlugin,
+    loot::LootPlugin,
+    pack::PackPlugin,
+    pathfinding::PathfindingPlugin,
     progress_bar::{ProgressBar, ProgressBarBundle, ProgressBarPlugin},
+    rng::GameRng,
+    status::StatusPlugin,
+    targeting::TargetingPlugin,
     tile::TilePlugin,
+    villager::VillagerPlugin,
+    wave::WavePlugin,
 };
 use bevy::prelude::*;
 
@@ -18,10 +52,27 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(CardPlugin)
+        app.init_resource::<GameRng>()
+            .add_plugins(BuildingPlugin)
+            .add_plugins(CardPlugin)
+            .add_plugins(CombatPlugin)
+            .add_plugins(DayCyclePlugin)
+            .add_plugins(EconomyPlugin)
+            .add_plugins(EnemyPlugin)
+            .add_plugins(EquipmentPlugin)
+            .add_plugins(LootPlugin)
+            .add_plugins(PackPlugin)
+            .add_plugins(FoodPlugin)
+            .add_plugins(StatusPlugin)
+            .add_plugins(TargetingPlugin)
+            .add_plugins(PathfindingPlugin)
             .add_plugins(PlayerCameraPlugin)
             .add_plugins(ProgressBarPlugin)
             .add_plugins(TilePlugin)
+            .add_plugins(VillagerPlugin)
+            .add_plugins(ExplorationPlugin)
+            .add_plugins(LabelPlugin)
+            .add_plugins(WavePlugin)
             .add_systems(Startup, setup);
     }
 }
@@ -0,0 +1,223 @@
+use std::{f32::consts::PI, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::game::{
+    animate::{AnimateRange, Ease},
+    buildings::Housing,
+    card::{despawn_card, Card, CardType, FaceDown, SelectedCard, StackRoots},
+    economy::pays,
+    loot::{spawn_drops, CardFlight, DropTable},
+    rng::GameRng,
+};
+
+pub struct PackPlugin;
+
+impl Plugin for PackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, open_packs.before(crate::game::card::select_card))
+            .add_systems(Update, reveal_cards.after(crate::game::loot::fly_cards));
+    }
+}
+
+/// What a pack bought at the market holds. Better packs go on sale as the days go by.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PackTheme {
+    Harvest,
+    Settlers,
+    Armory,
+}
+
+impl PackTheme {
+    pub const ALL: [PackTheme; 3] = [PackTheme::Harvest, PackTheme::Settlers, PackTheme::Armory];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PackTheme::Harvest => "Harvest",
+            PackTheme::Settlers => "Settlers",
+            PackTheme::Armory => "Armory",
+        }
+    }
+
+    /// How many coins the pack costs.
+    pub fn price(&self) -> usize {
+        match self {
+            PackTheme::Harvest => 3,
+            PackTheme::Settlers => 6,
+            PackTheme::Armory => 8,
+        }
+    }
+
+    /// The day from which the market sells the pack.
+    pub fn unlock_day(&self) -> u32 {
+        match self {
+            PackTheme::Harvest => 1,
+            PackTheme::Settlers => 2,
+            PackTheme::Armory => 3,
+        }
+    }
+
+    pub fn contents(&self) -> DropTable {
+        match self {
+            PackTheme::Harvest => DropTable {
+                rolls: 3,
+                weighted: &[
+                    (Some(CardType::Berry), 3),
+                    (Some(CardType::Log), 2),
+                    (Some(CardType::Coin), 1),
+                ],
+                rare: &[(CardType::Hoe, 0.15)],
+                ..DropTable::EMPTY
+            },
+            PackTheme::Settlers => DropTable {
+                guaranteed: &[CardType::Berry],
+                rolls: 2,
+                weighted: &[(Some(CardType::Log), 1), (Some(CardType::Berry), 1)],
+                rare: &[(CardType::Villager, 0.35)],
+            },
+            PackTheme::Armory => DropTable {
+                guaranteed: &[CardType::Log],
+                rolls: 2,
+                weighted: &[
+                    (Some(CardType::Sword), 1),
+                    (Some(CardType::Shield), 1),
+                    (Some(CardType::Axe), 1),
+                ],
+                ..DropTable::EMPTY
+            },
+        }
+    }
+
+    /// Tint of the pack's portrait.
+    pub fn color(&self) -> Color {
+        match self {
+            PackTheme::Harvest => Color::rgb(0.6, 0.8, 0.4),
+            PackTheme::Settlers => Color::rgb(0.85, 0.7, 0.45),
+            PackTheme::Armory => Color::rgb(0.6, 0.65, 0.8),
+        }
+    }
+
+    /// The most expensive pack on sale on `day` that a stack holding these card types pays for.
+    pub fn best_affordable(stack: &[CardType], day: u32) -> Option<PackTheme> {
+        PackTheme::ALL
+            .iter()
+            .rev()
+            .find(|theme| theme.unlock_day() <= day && pays(stack, theme.price()))
+            .copied()
+    }
+}
+
+/// Turns a card over once it has landed, after a delay so the cards of a pack flip one by one.
+#[derive(Component)]
+pub struct CardReveal {
+    pub delay: Timer,
+    pub flip: AnimateRange,
+}
+
+impl CardReveal {
+    pub const STAGGER: f32 = 0.15;
+    pub const FLIP_TIME: f32 = 0.3;
+
+    pub fn new(order: usize) -> Self {
+        Self {
+            delay: Timer::from_seconds(order as f32 * Self::STAGGER, TimerMode::Once),
+            flip: AnimateRange::new(
+                Duration::from_secs_f32(Self::FLIP_TIME),
+                Ease::Linear,
+                0.0..1.0,
+                false,
+            ),
+        }
+    }
+}
+
+/// How far a pack may move between being picked up and put down to count as a click.
+const CLICK_DISTANCE: f32 = 0.1;
+
+/// Packs open when clicked, spreading their cards face down around where they were. Villagers
+/// only come out of a pack while the village has room for them.
+#[allow(clippy::too_many_arguments)]
+fn open_packs(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    housing: Res<Housing>,
+    mut selected: ResMut<SelectedCard>,
+    mut rng: ResMut<GameRng>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut picked: Local<Option<(Entity, Vec2)>>,
+    mut cards: Query<&mut Card>,
+    transforms: Query<&Transform, With<Card>>,
+) {
+    let held = match *selected {
+        SelectedCard::Some(entity) => cards
+            .get(entity)
+            .ok()
+            .zip(transforms.get(entity).ok())
+            .and_then(|(card, transform)| match card.card_type() {
+                CardType::Pack(theme) => Some((entity, theme, transform.translation)),
+                _ => None,
+            }),
+        SelectedCard::None => None,
+    };
+    let Some((entity, theme, translation)) = held else {
+        *picked = None;
+        return;
+    };
+    let position = translation.truncate();
+    if !matches!(*picked, Some((picked, _)) if picked == entity) {
+        *picked = Some((entity, position));
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some((_, from)) = picked.take() else {
+        return;
+    };
+    if from.distance(position) > CLICK_DISTANCE {
+        return;
+    }
+
+    // let go of the pack here, it is gone by the time the release is handled
+    *selected = SelectedCard::None;
+    despawn_card(&mut commands, &mut stack_roots, &mut cards, entity);
+    let origin = position.extend(0.0);
+    let mut drops = theme.contents().roll(&mut rng);
+    let mut room = housing.capacity.saturating_sub(housing.population);
+    drops.retain(|card_type| {
+        if *card_type != CardType::Villager {
+            return true;
+        }
+        let fits = room > 0;
+        room = room.saturating_sub(1);
+        fits
+    });
+    for (i, card) in spawn_drops(&mut commands, &drops, origin, origin)
+        .into_iter()
+        .enumerate()
+    {
+        commands.entity(card).insert((FaceDown, CardReveal::new(i)));
+    }
+}
+
+/// Flips cards by squashing them to an edge and back, showing their face from halfway.
+fn reveal_cards(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut cards: Query<(Entity, &mut CardReveal, &mut Transform, Has<FaceDown>), Without<CardFlight>>,
+) {
+    for (entity, mut reveal, mut transform, face_down) in &mut cards {
+        if !reveal.delay.tick(time.delta()).finished() {
+            continue;
+        }
+        let t = reveal.flip.tick(time.delta());
+        // colliders don't like a scale of zero
+        transform.scale.x = (t * PI).cos().abs().max(0.01);
+        if t >= 0.5 && face_down {
+            commands.entity(entity).remove::<FaceDown>();
+        }
+        if reveal.flip.finished() {
+            transform.scale.x = 1.0;
+            commands.entity(entity).remove::<CardReveal>();
+        }
+    }
+}
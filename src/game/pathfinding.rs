@@ -0,0 +1,304 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::game::{
+    exploration::FogGrid,
+    tile::{Tile, TileGrid},
+};
+
+pub struct PathfindingPlugin;
+
+impl Plugin for PathfindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .add_systems(Update, update_nav_grid);
+    }
+}
+
+/// How costly each grid location is to walk through, rebuilt whenever tiles change. Locations
+/// without a tile, unexplored ones included, are open ground.
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    costs: HashMap<IVec2, Option<f32>>,
+    min: IVec2,
+    max: IVec2,
+    /// Bumped on every rebuild, so paths found on an older grid get searched again.
+    pub version: u32,
+}
+
+impl NavGrid {
+    /// How far past the known world paths may lead.
+    pub const MARGIN: i32 = 1;
+
+    /// `None` if the location can't be crossed at all.
+    pub fn cost(&self, location: IVec2) -> Option<f32> {
+        self.costs.get(&location).copied().unwrap_or(Some(1.0))
+    }
+
+    pub fn passable(&self, translation: Vec3) -> bool {
+        self.cost(Tile::translation_to_grid(translation)).is_some()
+    }
+
+    /// A* over the grid, returning the locations to walk through after `from`, ending with `to`.
+    /// The goal itself is always enterable, so cards standing on a lake can still be reached.
+    pub fn find_path(&self, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        let min = self.min.min(from).min(to) - IVec2::splat(Self::MARGIN);
+        let max = self.max.max(from).max(to) + IVec2::splat(Self::MARGIN);
+        let estimate = |location: IVec2| manhattan(to, location) as f32;
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<IVec2, IVec2> = HashMap::new();
+        let mut best: HashMap<IVec2, f32> = HashMap::new();
+        open.push(Node {
+            estimate: estimate(from),
+            location: from,
+        });
+        best.insert(from, 0.0);
+
+        while let Some(Node { location, .. }) = open.pop() {
+            if location == to {
+                let mut path = vec![to];
+                let mut current = to;
+                while let Some(previous) = came_from.get(&current).filter(|p| **p != from) {
+                    path.push(*previous);
+                    current = *previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let so_far = best[&location];
+            for offset in Tile::NEIGHBOURS {
+                let next = location + offset;
+                if next.cmplt(min).any() || next.cmpgt(max).any() {
+                    continue;
+                }
+                let step = match self.cost(next) {
+                    Some(cost) => cost,
+                    None if next == to => 1.0,
+                    None => continue,
+                };
+                let cost = so_far + step;
+                if best.get(&next).is_some_and(|known| *known <= cost) {
+                    continue;
+                }
+                best.insert(next, cost);
+                came_from.insert(next, location);
+                open.push(Node {
+                    estimate: cost + estimate(next),
+                    location: next,
+                });
+            }
+        }
+        None
+    }
+}
+
+fn manhattan(a: IVec2, b: IVec2) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+#[derive(PartialEq)]
+struct Node {
+    estimate: f32,
+    location: IVec2,
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    // reversed, so the heap pops the lowest estimate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate).then_with(|| {
+            (other.location.x, other.location.y).cmp(&(self.location.x, self.location.y))
+        })
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The grid locations a card still has to walk through to reach its goal.
+#[derive(Component, Default)]
+pub struct Path {
+    pub goal: Option<IVec2>,
+    /// Next location last, `None` if the goal can't be reached.
+    pub waypoints: Option<Vec<IVec2>>,
+    pub version: u32,
+}
+
+pub fn step_towards(transform: &mut Transform, target: Vec3, distance: f32) {
+    let direction = (target - transform.translation)
+        .truncate()
+        .normalize_or_zero();
+    transform.translation += direction.extend(0.0) * distance;
+}
+
+/// Moves up to `distance` towards `target`, around tiles that can't be crossed and slowed down
+/// by the tile underneath. Returns false if there is no way to get there.
+pub fn walk_towards(
+    transform: &mut Transform,
+    target: Vec3,
+    distance: f32,
+    nav_grid: &NavGrid,
+    path: &mut Path,
+) -> bool {
+    let from = Tile::translation_to_grid(transform.translation);
+    let goal = Tile::translation_to_grid(target);
+    let strayed = path
+        .waypoints
+        .as_ref()
+        .and_then(|waypoints| waypoints.last())
+        .is_some_and(|next| manhattan(*next, from) > 1);
+    if path.goal != Some(goal) || path.version != nav_grid.version || strayed {
+        path.goal = Some(goal);
+        path.version = nav_grid.version;
+        path.waypoints = nav_grid.find_path(from, goal).map(|mut waypoints| {
+            waypoints.reverse();
+            waypoints
+        });
+    }
+
+    let Some(waypoints) = &mut path.waypoints else {
+        return false;
+    };
+    if waypoints.last() == Some(&from) {
+        waypoints.pop();
+    }
+    let towards = match waypoints.last() {
+        Some(next) => Tile::grid_to_translation(*next),
+        None => target,
+    };
+    let slowdown = nav_grid.cost(from).unwrap_or(1.0);
+    step_towards(transform, towards, distance / slowdown);
+    true
+}
+
+pub fn update_nav_grid(
+    mut nav_grid: ResMut<NavGrid>,
+    tile_grid: Res<TileGrid>,
+    fog_grid: Res<FogGrid>,
+    tiles: Query<&Tile>,
+) {
+    if !tile_grid.is_changed() && !fog_grid.is_changed() {
+        return;
+    }
+
+    nav_grid.costs.clear();
+    for (location, entity) in tile_grid.iter() {
+        if let Ok(tile) = tiles.get(*entity) {
+            nav_grid.costs.insert(*location, tile.traversal_cost());
+        }
+    }
+    let mut locations = tile_grid.keys().chain(fog_grid.keys());
+    if let Some(first) = locations.next() {
+        let (min, max) = locations.fold((*first, *first), |(min, max), location| {
+            (min.min(*location), max.max(*location))
+        });
+        nav_grid.min = min;
+        nav_grid.max = max;
+    }
+    nav_grid.version = nav_grid.version.wrapping_add(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid spanning `min` to `max` with lakes at the given locations.
+    fn grid_with_lakes(min: IVec2, max: IVec2, lakes: &[IVec2]) -> NavGrid {
+        NavGrid {
+            costs: lakes.iter().map(|lake| (*lake, None)).collect(),
+            min,
+            max,
+            version: 0,
+        }
+    }
+
+    /// Checks the path walks one location at a time from `from`, without crossing a lake.
+    fn assert_walkable(grid: &NavGrid, from: IVec2, path: &[IVec2]) {
+        let mut current = from;
+        for next in path {
+            assert_eq!(manhattan(current, *next), 1, "{current} to {next}");
+            assert!(grid.cost(*next).is_some(), "{next} is a lake");
+            current = *next;
+        }
+    }
+
+    #[test]
+    fn paths_lead_around_lakes() {
+        let grid = grid_with_lakes(IVec2::ZERO, IVec2::new(2, 2), &[IVec2::new(1, 0)]);
+        let from = IVec2::ZERO;
+        let to = IVec2::new(2, 0);
+
+        let path = grid.find_path(from, to).unwrap();
+
+        assert_eq!(path.len(), 4);
+        assert_eq!(path.last(), Some(&to));
+        assert_walkable(&grid, from, &path);
+    }
+
+    #[test]
+    fn walled_off_goals_cannot_be_reached() {
+        let goal = IVec2::new(1, 1);
+        let walls = Tile::NEIGHBOURS.map(|offset| goal + offset);
+        let grid = grid_with_lakes(IVec2::ZERO, IVec2::new(2, 2), &walls);
+
+        assert_eq!(grid.find_path(IVec2::ZERO, goal), None);
+    }
+
+    #[test]
+    fn goals_on_a_lake_can_still_be_reached() {
+        let lake = IVec2::new(2, 0);
+        let grid = grid_with_lakes(IVec2::ZERO, IVec2::new(2, 2), &[lake]);
+
+        assert_eq!(
+            grid.find_path(IVec2::ZERO, lake),
+            Some(vec![IVec2::new(1, 0), lake])
+        );
+    }
+
+    #[test]
+    fn paths_stay_within_the_margin() {
+        let min = IVec2::ZERO;
+        let max = IVec2::new(2, 0);
+        let from = IVec2::ZERO;
+        let to = IVec2::new(2, 0);
+
+        // the way around the top is the one row of margin past the known world
+        let grid = grid_with_lakes(min, max, &[IVec2::new(1, 0), IVec2::new(1, -1)]);
+        let path = grid.find_path(from, to).unwrap();
+        assert_walkable(&grid, from, &path);
+        assert!(path.contains(&IVec2::new(1, max.y + NavGrid::MARGIN)));
+
+        // closing that row leaves no way around within the margin
+        let wall = [IVec2::new(1, 1), IVec2::new(1, 0), IVec2::new(1, -1)];
+        let grid = grid_with_lakes(min, max, &wall);
+        assert_eq!(grid.find_path(from, to), None);
+    }
+
+    #[test]
+    fn walking_searches_again_when_the_grid_changes() {
+        let mut grid = grid_with_lakes(IVec2::ZERO, IVec2::new(2, 2), &[]);
+        let mut path = Path::default();
+        let mut transform = Transform::from_translation(Tile::grid_to_translation(IVec2::ZERO));
+        let target = Tile::grid_to_translation(IVec2::new(2, 0));
+        let blocked = IVec2::new(1, 0);
+
+        assert!(walk_towards(&mut transform, target, 0.0, &grid, &mut path));
+        assert!(path.waypoints.as_ref().unwrap().contains(&blocked));
+
+        // the old path is kept until the grid is rebuilt
+        grid.costs.insert(blocked, None);
+        assert!(walk_towards(&mut transform, target, 0.0, &grid, &mut path));
+        assert!(path.waypoints.as_ref().unwrap().contains(&blocked));
+
+        grid.version += 1;
+        assert!(walk_towards(&mut transform, target, 0.0, &grid, &mut path));
+        assert_eq!(path.version, grid.version);
+        assert!(!path.waypoints.as_ref().unwrap().contains(&blocked));
+    }
+}
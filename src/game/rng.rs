@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(StdRng);
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_seed(Self::DEFAULT_SEED)
+    }
+}
+
+impl GameRng {
+    pub const DEFAULT_SEED: u64 = 0xCA4D_C0B1;
+
+    pub fn from_seed(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+
+    /// Picks one of the entries with a probability proportional to its weight, or nothing if
+    /// none of them has any weight.
+    pub fn weighted<T: Copy>(&mut self, entries: &[(T, u32)]) -> Option<T> {
+        let total: u32 = entries.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = self.0.gen_range(0..total);
+        for (value, weight) in entries {
+            if roll < *weight {
+                return Some(*value);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
@@ -0,0 +1,372 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::game::{
+    card::{despawn_card, kill_card, Card, CardClass, CardDied, SelectedCard, StackRoots},
+    tile::{Tile, TileGrid},
+};
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StatusIcons>()
+            .add_systems(
+                PostUpdate,
+                on_spawn_card.after(crate::game::card::on_spawn_card),
+            )
+            .add_systems(Update, eat_food.after(crate::game::card::evaluate_stacks))
+            .add_systems(Update, apply_tile_effects)
+            .add_systems(
+                Update,
+                tick_status_effects
+                    .after(crate::game::card::combat)
+                    .after(eat_food)
+                    .after(apply_tile_effects),
+            )
+            .add_systems(Update, show_status_icons.after(tick_status_effects));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum StatusKind {
+    /// Deals damage every few seconds, more with every stack.
+    Poison,
+    /// Pauses attacks and keeps enemies from moving.
+    Stun,
+    /// Deals damage every second, more with every stack, but wears off quickly.
+    Bleed,
+    /// Restores health every few seconds.
+    Regeneration,
+}
+
+impl StatusKind {
+    pub const ALL: [StatusKind; 4] = [
+        StatusKind::Poison,
+        StatusKind::Stun,
+        StatusKind::Bleed,
+        StatusKind::Regeneration,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StatusKind::Poison => "poison",
+            StatusKind::Stun => "stun",
+            StatusKind::Bleed => "bleed",
+            StatusKind::Regeneration => "regen",
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        match self {
+            StatusKind::Poison => 6.0,
+            StatusKind::Stun => 1.5,
+            StatusKind::Bleed => 3.0,
+            StatusKind::Regeneration => 8.0,
+        }
+    }
+
+    /// Seconds between two ticks of the effect.
+    pub fn interval(&self) -> f32 {
+        match self {
+            StatusKind::Poison | StatusKind::Regeneration => 2.0,
+            StatusKind::Stun | StatusKind::Bleed => 1.0,
+        }
+    }
+
+    /// Effects applied again while still active refresh their duration, and those with more
+    /// than one stack also grow stronger.
+    pub fn max_stacks(&self) -> u32 {
+        match self {
+            StatusKind::Poison | StatusKind::Bleed => 3,
+            StatusKind::Stun | StatusKind::Regeneration => 1,
+        }
+    }
+
+    /// How much health a single tick takes or gives.
+    pub fn health_per_tick(&self, stacks: u32) -> isize {
+        match self {
+            StatusKind::Poison | StatusKind::Bleed => -(stacks as isize),
+            StatusKind::Stun => 0,
+            StatusKind::Regeneration => stacks as isize,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            StatusKind::Poison => Color::rgb(0.45, 0.8, 0.2),
+            StatusKind::Stun => Color::rgb(0.95, 0.85, 0.2),
+            StatusKind::Bleed => Color::rgb(0.6, 0.05, 0.1),
+            StatusKind::Regeneration => Color::rgb(0.4, 0.85, 0.9),
+        }
+    }
+}
+
+/// A chance to apply a status effect to the card hit by an attack.
+#[derive(Clone, Copy, Debug)]
+pub struct Infliction {
+    pub kind: StatusKind,
+    pub chance: f32,
+}
+
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub stacks: u32,
+    remaining: Timer,
+    tick: Timer,
+}
+
+impl StatusEffect {
+    fn new(kind: StatusKind) -> Self {
+        Self {
+            kind,
+            stacks: 1,
+            remaining: Timer::from_seconds(kind.duration(), TimerMode::Once),
+            tick: Timer::from_seconds(kind.interval(), TimerMode::Repeating),
+        }
+    }
+}
+
+/// The status effects currently on a card.
+#[derive(Default)]
+pub struct StatusEffects(Vec<StatusEffect>);
+
+impl StatusEffects {
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.0.iter().find(|effect| effect.kind == kind)
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.get(StatusKind::Stun).is_some()
+    }
+
+    /// Adds the effect, or refreshes and stacks it if the card already has it.
+    pub fn apply(&mut self, kind: StatusKind) {
+        match self.0.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => {
+                effect.stacks = (effect.stacks + 1).min(kind.max_stacks());
+                effect.remaining.reset();
+            }
+            None => self.0.push(StatusEffect::new(kind)),
+        }
+    }
+
+    /// Adds the effect, or only refreshes it if the card already has it.
+    pub fn sustain(&mut self, kind: StatusKind) {
+        match self.0.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.remaining.reset(),
+            None => self.0.push(StatusEffect::new(kind)),
+        }
+    }
+
+    /// Advances all effects, drops the ones that ran out and returns the change in health.
+    pub fn tick(&mut self, delta: Duration) -> isize {
+        let mut health = 0;
+        for effect in &mut self.0 {
+            let ticks = effect.tick.tick(delta).times_finished_this_tick();
+            health += effect.kind.health_per_tick(effect.stacks) * ticks as isize;
+            effect.remaining.tick(delta);
+        }
+        self.0.retain(|effect| !effect.remaining.finished());
+        health
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &StatusEffect> {
+        self.0.iter()
+    }
+}
+
+/// Shows one of the status effects of the card it is a child of.
+#[derive(Component)]
+pub struct StatusIcon(pub StatusKind);
+
+#[derive(Resource)]
+pub struct StatusIcons {
+    mesh: Handle<Mesh>,
+    materials: Vec<(StatusKind, Handle<StandardMaterial>)>,
+}
+
+impl StatusIcons {
+    pub const RADIUS: f32 = 0.03;
+    pub const SPACING: f32 = 0.08;
+    /// Where the first icon goes, on the left just below the hearts.
+    pub const ORIGIN: Vec3 = Vec3::new(-0.3, 0.3, 0.01);
+
+    fn material(&self, kind: StatusKind) -> Handle<StandardMaterial> {
+        self.materials
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, material)| material.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl FromWorld for StatusIcons {
+    fn from_world(world: &mut World) -> Self {
+        let world = world.cell();
+        let mut meshes = world.resource_mut::<Assets<Mesh>>();
+        let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+        Self {
+            mesh: meshes.add(Circle::new(Self::RADIUS)),
+            materials: StatusKind::ALL
+                .iter()
+                .map(|kind| {
+                    let material = materials.add(StandardMaterial {
+                        base_color: kind.color(),
+                        unlit: true,
+                        alpha_mode: AlphaMode::Blend,
+                        depth_bias: 0.1,
+                        ..default()
+                    });
+                    (*kind, material)
+                })
+                .collect(),
+        }
+    }
+}
+
+fn on_spawn_card(
+    mut commands: Commands,
+    icons: Res<StatusIcons>,
+    cards: Query<(Entity, &Card), Added<Card>>,
+) {
+    for (entity, card) in &cards {
+        if card.info.stats.max_health == 0 {
+            continue;
+        }
+        commands.entity(entity).with_children(|parent| {
+            for kind in StatusKind::ALL {
+                parent.spawn((
+                    StatusIcon(kind),
+                    PbrBundle {
+                        material: icons.material(kind),
+                        mesh: icons.mesh.clone(),
+                        transform: Transform::from_translation(StatusIcons::ORIGIN),
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Wounded villagers eat food cards dropped on them and get the food's effect. Food stacked on a
+/// healthy villager is left for the next feeding.
+pub fn eat_food(
+    mut commands: Commands,
+    selected: Res<SelectedCard>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<&mut Card>,
+    card_entities: Query<Entity, With<Card>>,
+) {
+    for entity in &card_entities {
+        let Ok(card) = cards.get(entity) else {
+            continue;
+        };
+        let (Some(kind), Some(eater)) = (card.card_type().food_effect(), card.stack_parent) else {
+            continue;
+        };
+        if selected.is_selected(eater)
+            || !cards.get(eater).is_ok_and(|eater| {
+                eater.class() == CardClass::Villager
+                    && eater.info.stats.health < eater.info.stats.max_health as isize
+            })
+        {
+            continue;
+        }
+        if let Ok(mut eater) = cards.get_mut(eater) {
+            eater.effects.apply(kind);
+        }
+        despawn_card(&mut commands, &mut stack_roots, &mut cards, entity);
+    }
+}
+
+/// Tiles keep their effect going on the villagers standing on them. Villagers carried over a
+/// tile and those slotted into a camp to assault it are left alone.
+pub fn apply_tile_effects(
+    selected: Res<SelectedCard>,
+    tile_grid: Res<TileGrid>,
+    tiles: Query<&Tile>,
+    mut cards: Query<(Entity, &mut Card, &Transform)>,
+) {
+    for (entity, mut card, transform) in &mut cards {
+        if card.class() != CardClass::Villager || selected.is_selected(entity) {
+            continue;
+        }
+        let location = Tile::translation_to_grid(transform.translation);
+        let Some((tile_entity, tile)) = tile_grid
+            .get(&location)
+            .and_then(|tile| Some((*tile, tiles.get(*tile).ok()?)))
+        else {
+            continue;
+        };
+        if matches!(tile, Tile::Enemies { .. }) && card.slotted_in_tile == Some(tile_entity) {
+            continue;
+        }
+        if let Some(kind) = tile.status_effect() {
+            card.effects.sustain(kind);
+        }
+    }
+}
+
+pub fn tick_status_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    selected: Res<SelectedCard>,
+    mut deaths: EventWriter<CardDied>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<&mut Card>,
+    transforms: Query<(Entity, &Transform), With<Card>>,
+) {
+    for (entity, transform) in &transforms {
+        // effects wait for a card that is picked up, so it can't die in hand
+        if selected.is_selected(entity) {
+            continue;
+        }
+        let Ok(mut card) = cards.get_mut(entity) else {
+            continue;
+        };
+        let change = card.effects.tick(time.delta());
+        if change == 0 {
+            continue;
+        }
+        let stats = &mut card.info.stats;
+        stats.health = (stats.health + change).clamp(0, stats.max_health as isize);
+        if stats.health == 0 {
+            kill_card(
+                &mut commands,
+                &mut stack_roots,
+                &mut cards,
+                &mut deaths,
+                entity,
+                transform.translation,
+            );
+        }
+    }
+}
+
+/// Lines up the icons of the active effects, bigger the more stacks they have.
+fn show_status_icons(
+    cards: Query<(&Card, &Children)>,
+    mut icons: Query<(&StatusIcon, &mut Visibility, &mut Transform)>,
+) {
+    for (card, children) in &cards {
+        let mut shown = 0;
+        for child in children {
+            let Ok((icon, mut visibility, mut transform)) = icons.get_mut(*child) else {
+                continue;
+            };
+            let Some(effect) = card.effects.get(icon.0) else {
+                *visibility = Visibility::Hidden;
+                continue;
+            };
+            *visibility = Visibility::Inherited;
+            transform.translation =
+                StatusIcons::ORIGIN + Vec3::X * shown as f32 * StatusIcons::SPACING;
+            transform.scale = Vec3::splat(1.0 + 0.2 * (effect.stacks - 1) as f32);
+            shown += 1;
+        }
+    }
+}
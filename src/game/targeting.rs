@@ -0,0 +1,412 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::game::{
+    buildings::Storage,
+    card::{Card, CardClass, SelectedCard, StackRoots},
+    economy::worth,
+};
+
+pub struct TargetingPlugin;
+
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>().add_systems(
+            Update,
+            index_cards.after(crate::game::card::evaluate_stacks),
+        );
+    }
+}
+
+/// A snapshot of a card, as far as enemies care when picking targets.
+#[derive(Clone, Copy, Debug)]
+pub struct Sighting {
+    pub entity: Entity,
+    pub class: CardClass,
+    pub translation: Vec3,
+    pub health: isize,
+    pub slotted: bool,
+    pub stealable: bool,
+    /// What all cards of the stack this card is part of are worth together, in coins.
+    pub stack_value: usize,
+}
+
+impl Sighting {
+    pub fn distance(&self, position: Vec3) -> f32 {
+        self.translation.truncate().distance(position.truncate())
+    }
+}
+
+/// All cards bucketed into square cells, rebuilt every frame, so that target searches only look
+/// at cards close by.
+#[derive(Resource, Default)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<Sighting>>,
+    cards: HashMap<Entity, Sighting>,
+}
+
+impl SpatialGrid {
+    pub const CELL_SIZE: f32 = 2.0;
+    pub const MAX_SEARCH_RADIUS: f32 = 40.0;
+
+    fn cell(translation: Vec3) -> IVec2 {
+        (translation.truncate() / Self::CELL_SIZE)
+            .floor()
+            .as_ivec2()
+    }
+
+    pub fn insert(&mut self, sighting: Sighting) {
+        self.cells
+            .entry(Self::cell(sighting.translation))
+            .or_default()
+            .push(sighting);
+        self.cards.insert(sighting.entity, sighting);
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.cards.clear();
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&Sighting> {
+        self.cards.get(&entity)
+    }
+
+    /// Cards whose distance to `center` is at most `radius`.
+    pub fn within(&self, center: Vec3, radius: f32) -> impl Iterator<Item = &Sighting> {
+        let min = Self::cell(center - Vec3::splat(radius));
+        let max = Self::cell(center + Vec3::splat(radius));
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(move |sighting| sighting.distance(center) <= radius)
+    }
+
+    /// Searches ring by ring around `center` and stops once no further ring can hold anything
+    /// closer than what was already found.
+    pub fn nearest(&self, center: Vec3, filter: impl Fn(&Sighting) -> bool) -> Option<&Sighting> {
+        let origin = Self::cell(center);
+        let max_ring = (Self::MAX_SEARCH_RADIUS / Self::CELL_SIZE).ceil() as i32;
+        let mut best: Option<(&Sighting, f32)> = None;
+        for ring in 0..=max_ring {
+            for x in -ring..=ring {
+                for y in -ring..=ring {
+                    if x.abs() != ring && y.abs() != ring {
+                        continue;
+                    }
+                    let Some(cell) = self.cells.get(&(origin + IVec2::new(x, y))) else {
+                        continue;
+                    };
+                    for sighting in cell.iter().filter(|s| filter(s)) {
+                        let distance = sighting.distance(center);
+                        if !best.is_some_and(|(_, best_distance)| best_distance <= distance) {
+                            best = Some((sighting, distance));
+                        }
+                    }
+                }
+            }
+            if let Some((sighting, distance)) = best {
+                if distance <= ring as f32 * Self::CELL_SIZE {
+                    return Some(sighting);
+                }
+            }
+        }
+        best.map(|(sighting, _)| sighting)
+    }
+}
+
+/// How an enemy picks its target among the cards it is after.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TargetingStrategy {
+    Nearest,
+    /// The card with the least health close by.
+    Weakest,
+    /// A card of the stack close by that is worth the most coins.
+    HighestValueStack,
+    /// A villager working in a tile slot, wherever it is.
+    SlottedWorker,
+}
+
+impl TargetingStrategy {
+    /// How far the strategies comparing cards look before settling for the nearest one.
+    pub const SEARCH_RADIUS: f32 = 8.0;
+
+    pub fn pick(
+        &self,
+        grid: &SpatialGrid,
+        position: Vec3,
+        prey: impl Fn(&Sighting) -> bool + Copy,
+    ) -> Option<Sighting> {
+        let by_distance =
+            |a: &&Sighting, b: &&Sighting| a.distance(position).total_cmp(&b.distance(position));
+        let picked = match self {
+            TargetingStrategy::Nearest => None,
+            TargetingStrategy::Weakest => grid
+                .within(position, Self::SEARCH_RADIUS)
+                .filter(|s| prey(s))
+                .min_by(|a, b| a.health.cmp(&b.health).then(by_distance(a, b))),
+            TargetingStrategy::HighestValueStack => grid
+                .within(position, Self::SEARCH_RADIUS)
+                .filter(|s| prey(s))
+                .max_by(|a, b| a.stack_value.cmp(&b.stack_value).then(by_distance(b, a))),
+            TargetingStrategy::SlottedWorker => grid.nearest(position, |s| prey(s) && s.slotted),
+        };
+        picked.or_else(|| grid.nearest(position, prey)).copied()
+    }
+
+    /// Picks among the given cards only, like the opponents in a fight.
+    pub fn pick_among<'a>(
+        &self,
+        candidates: impl Iterator<Item = &'a Sighting>,
+        position: Vec3,
+    ) -> Option<&'a Sighting> {
+        let by_distance =
+            |a: &&Sighting, b: &&Sighting| a.distance(position).total_cmp(&b.distance(position));
+        match self {
+            TargetingStrategy::Nearest => candidates.min_by(by_distance),
+            TargetingStrategy::Weakest => {
+                candidates.min_by(|a, b| a.health.cmp(&b.health).then(by_distance(a, b)))
+            }
+            TargetingStrategy::HighestValueStack => {
+                candidates.max_by(|a, b| a.stack_value.cmp(&b.stack_value).then(by_distance(b, a)))
+            }
+            TargetingStrategy::SlottedWorker => {
+                candidates.min_by(|a, b| b.slotted.cmp(&a.slotted).then(by_distance(a, b)))
+            }
+        }
+    }
+}
+
+/// The current target of an enemy. It is picked again right away when the target is gone, and
+/// every [`Targeting::RETARGET_INTERVAL`] seconds in case something better came along.
+#[derive(Component)]
+pub struct Targeting {
+    pub strategy: TargetingStrategy,
+    pub target: Option<Entity>,
+    pub retarget: Timer,
+}
+
+impl Targeting {
+    pub const RETARGET_INTERVAL: f32 = 1.0;
+
+    pub fn new(strategy: TargetingStrategy) -> Self {
+        Self {
+            strategy,
+            target: None,
+            retarget: Timer::from_seconds(Self::RETARGET_INTERVAL, TimerMode::Repeating),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        delta: std::time::Duration,
+        grid: &SpatialGrid,
+        position: Vec3,
+        prey: impl Fn(&Sighting) -> bool + Copy,
+    ) -> Option<Sighting> {
+        let due = self.retarget.tick(delta).just_finished();
+        let current = self
+            .target
+            .and_then(|target| grid.get(target))
+            .filter(|s| prey(s))
+            .copied();
+        let target = match current {
+            Some(current) if !due => Some(current),
+            _ => self.strategy.pick(grid, position, prey),
+        };
+        self.target = target.map(|s| s.entity);
+        target
+    }
+}
+
+pub fn index_cards(
+    selected: Res<SelectedCard>,
+    stack_roots: Res<StackRoots>,
+    mut grid: ResMut<SpatialGrid>,
+    cards: Query<(Entity, &Card, &Transform)>,
+    storages: Query<&Transform, With<Storage>>,
+) {
+    grid.clear();
+    // resources lying close to a storage are locked away from thieves
+    let guarded = |translation: Vec3| {
+        storages.iter().any(|storage| {
+            storage
+                .translation
+                .truncate()
+                .distance(translation.truncate())
+                <= Storage::GUARD_RADIUS
+        })
+    };
+    // every card of a stack shares the worth of the whole stack
+    let mut stack_values: HashMap<Entity, usize> = HashMap::new();
+    for root in stack_roots.roots() {
+        let mut stack = Vec::new();
+        let mut value = 0;
+        let mut current = Some(root);
+        while let Some((entity, card, _)) = current.and_then(|entity| cards.get(entity).ok()) {
+            stack.push(entity);
+            value += worth(card);
+            current = card.stack_child;
+        }
+        stack_values.extend(stack.into_iter().map(|entity| (entity, value)));
+    }
+    for (entity, card, transform) in &cards {
+        grid.insert(Sighting {
+            entity,
+            class: card.class(),
+            translation: transform.translation,
+            health: card.info.stats.health,
+            slotted: card.slotted_in_tile.is_some(),
+            stealable: card.class() == CardClass::Resource
+                && card.slotted_in_tile.is_none()
+                && card.carried_by.is_none()
+                && card.stack_child.is_none()
+                && !selected.is_selected(entity)
+                && !guarded(transform.translation),
+            stack_value: stack_values
+                .get(&entity)
+                .copied()
+                .unwrap_or_else(|| worth(card)),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, time::Duration};
+
+    use super::*;
+
+    fn sighting(index: u32, x: f32, y: f32) -> Sighting {
+        Sighting {
+            entity: Entity::from_raw(index),
+            class: CardClass::Villager,
+            translation: Vec3::new(x, y, 0.0),
+            health: 3,
+            slotted: false,
+            stealable: false,
+            stack_value: 1,
+        }
+    }
+
+    fn grid(sightings: impl IntoIterator<Item = Sighting>) -> SpatialGrid {
+        let mut grid = SpatialGrid::default();
+        for sighting in sightings {
+            grid.insert(sighting);
+        }
+        grid
+    }
+
+    fn anyone(_: &Sighting) -> bool {
+        true
+    }
+
+    #[test]
+    fn nearest_looks_one_ring_further_for_something_closer() {
+        // close to the edge of its cell, the card in the next cell over is nearer than the one in
+        // the same cell
+        let same_cell = sighting(0, 0.1, 1.0);
+        let next_cell = sighting(1, 2.1, 1.0);
+        let grid = grid([same_cell, next_cell]);
+
+        let nearest = grid.nearest(Vec3::new(1.9, 1.0, 0.0), anyone).unwrap();
+
+        assert_eq!(nearest.entity, next_cell.entity);
+    }
+
+    #[test]
+    fn nearest_stops_once_no_ring_can_hold_anything_closer() {
+        let close = sighting(0, 1.5, 1.0);
+        let far = sighting(1, 20.0, 1.0);
+        let grid = grid([close, far]);
+        let looked_at = RefCell::new(Vec::new());
+
+        let nearest = grid.nearest(Vec3::new(1.0, 1.0, 0.0), |sighting| {
+            looked_at.borrow_mut().push(sighting.entity);
+            true
+        });
+
+        assert_eq!(nearest.unwrap().entity, close.entity);
+        assert_eq!(looked_at.into_inner(), [close.entity]);
+    }
+
+    #[test]
+    fn weakest_picks_the_card_with_the_least_health() {
+        let mut healthy = sighting(0, 1.0, 0.0);
+        healthy.health = 3;
+        let mut wounded = sighting(1, 3.0, 0.0);
+        wounded.health = 1;
+        let mut also_wounded = sighting(2, 5.0, 0.0);
+        also_wounded.health = 1;
+        let mut out_of_reach = sighting(3, 20.0, 0.0);
+        out_of_reach.health = 0;
+        let grid = grid([healthy, wounded, also_wounded, out_of_reach]);
+
+        let picked = TargetingStrategy::Weakest
+            .pick(&grid, Vec3::ZERO, anyone)
+            .unwrap();
+
+        // the nearer of the two
+        assert_eq!(picked.entity, wounded.entity);
+    }
+
+    #[test]
+    fn highest_value_stack_picks_the_most_valuable_card() {
+        let mut cheap = sighting(0, 1.0, 0.0);
+        cheap.stack_value = 1;
+        let mut rich = sighting(1, 3.0, 0.0);
+        rich.stack_value = 5;
+        let mut also_rich = sighting(2, 5.0, 0.0);
+        also_rich.stack_value = 5;
+        let mut out_of_reach = sighting(3, 20.0, 0.0);
+        out_of_reach.stack_value = 50;
+        let grid = grid([cheap, rich, also_rich, out_of_reach]);
+
+        let picked = TargetingStrategy::HighestValueStack
+            .pick(&grid, Vec3::ZERO, anyone)
+            .unwrap();
+
+        // the nearer of the two
+        assert_eq!(picked.entity, rich.entity);
+    }
+
+    #[test]
+    fn slotted_workers_are_hunted_down_wherever_they_are() {
+        let idle = sighting(0, 1.0, 0.0);
+        let mut worker = sighting(1, 30.0, 0.0);
+        worker.slotted = true;
+
+        let picked = TargetingStrategy::SlottedWorker
+            .pick(&grid([idle, worker]), Vec3::ZERO, anyone)
+            .unwrap();
+        assert_eq!(picked.entity, worker.entity);
+
+        // with nobody at work, the nearest card will do
+        let picked = TargetingStrategy::SlottedWorker
+            .pick(&grid([idle]), Vec3::ZERO, anyone)
+            .unwrap();
+        assert_eq!(picked.entity, idle.entity);
+    }
+
+    #[test]
+    fn targets_are_picked_again_when_they_leave_the_grid() {
+        let first = sighting(0, 1.0, 0.0);
+        let second = sighting(1, 3.0, 0.0);
+        let mut targeting = Targeting::new(TargetingStrategy::Nearest);
+
+        let target = targeting.update(Duration::ZERO, &grid([first, second]), Vec3::ZERO, anyone);
+        assert_eq!(target.unwrap().entity, first.entity);
+
+        let target = targeting.update(Duration::ZERO, &grid([second]), Vec3::ZERO, anyone);
+        assert_eq!(target.unwrap().entity, second.entity);
+        assert_eq!(targeting.target, Some(second.entity));
+
+        // a closer card only takes over once it is time to look around again
+        let grid = grid([first, second]);
+        let target = targeting.update(Duration::ZERO, &grid, Vec3::ZERO, anyone);
+        assert_eq!(target.unwrap().entity, second.entity);
+        let retarget = Duration::from_secs_f32(Targeting::RETARGET_INTERVAL);
+        let target = targeting.update(retarget, &grid, Vec3::ZERO, anyone);
+        assert_eq!(target.unwrap().entity, first.entity);
+    }
+}
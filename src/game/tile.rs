@@ -5,7 +5,16 @@ use bevy_rapier3d::prelude::Collider;
 
 use crate::game::{
     card::{Card, CardBundle, CardClass, CardType, HoverPoint, SelectedCard},
+    day_cycle::DayCycle,
+    exploration::{FogGrid, FogTile},
+    label::{hud_text, set_text, set_text_color, WorldLabelBundle},
+    loot::{spawn_drops, DropTable},
+    pack::PackTheme,
     progress_bar::{self, ProgressBar, ProgressBarBundle, ProgressBarStatus},
+    rng::GameRng,
+    status::StatusKind,
+    villager::reward_workers,
+    wave::{DifficultyCurve, WaveDirector},
 };
 
 pub struct TilePlugin;
@@ -18,7 +27,21 @@ impl Plugin for TilePlugin {
             .add_systems(Startup, spawn_tiles)
             .add_systems(PostUpdate, on_spawn_tile)
             .add_systems(Update, hover_tile.after(crate::game::card::select_card))
-            .add_systems(Update, evaluate_tiles.after(hover_tile));
+            .add_systems(Startup, spawn_tile_info_panel)
+            .add_systems(Update, compute_adjacency)
+            .add_systems(Update, clean_tile_slots.after(hover_tile))
+            .add_systems(
+                Update,
+                evaluate_tiles
+                    .after(clean_tile_slots)
+                    .after(compute_adjacency),
+            )
+            .add_systems(Update, construct_tiles.after(evaluate_tiles))
+            .add_systems(Update, assault_camps.after(clean_tile_slots))
+            .add_systems(Update, show_camp_health.after(assault_camps))
+            .add_systems(Update, label_tile_rates.after(evaluate_tiles))
+            .add_systems(Update, show_tile_resources.after(evaluate_tiles))
+            .add_systems(Update, show_tile_info.after(compute_adjacency));
     }
 }
 
@@ -26,10 +49,7 @@ fn spawn_tiles(mut commands: Commands, tile_data: Res<TileData>) {
     for x in -1..2 {
         for y in -1..2 {
             commands.spawn(TileBundle {
-                tile: Tile::Woods {
-                    slotted_villager: None,
-                    progress_bar: None,
-                },
+                tile: Tile::Woods { progress_bar: None },
                 tile_grid_location: TileGridLocation(IVec2::new(x, y)),
                 ..default()
             });
@@ -40,25 +60,36 @@ fn spawn_tiles(mut commands: Commands, tile_data: Res<TileData>) {
         tile_grid_location: TileGridLocation(IVec2::new(0, 2)),
         ..default()
     });
+    commands.spawn(TileBundle {
+        tile: Tile::Market,
+        tile_grid_location: TileGridLocation(IVec2::new(0, -2)),
+        ..default()
+    });
 }
 
 #[derive(Component, Clone, Copy, PartialEq, Eq)]
 pub enum Tile {
     Woods {
-        slotted_villager: Option<Entity>,
         progress_bar: Option<Entity>,
     },
     Enemies {
         progress_bar: Option<Entity>,
     },
+    Farm {
+        progress_bar: Option<Entity>,
+    },
+    Lake,
+    LumberCamp {
+        progress_bar: Option<Entity>,
+    },
+    Cleared,
+    /// Buys any player card dropped on it for coins, see `economy`.
+    Market,
 }
 
 impl Default for Tile {
     fn default() -> Self {
-        Self::Woods {
-            slotted_villager: None,
-            progress_bar: None,
-        }
+        Self::Woods { progress_bar: None }
     }
 }
 
@@ -68,6 +99,25 @@ impl Tile {
     pub const TILE_SLOT_ASPECT_RATIO: f32 = 50.0 / 60.0;
     pub const TILE_SLOT_SIZE: f32 = 1.2;
     pub const SPAWN_OFFSET: f32 = 0.95;
+    pub const WORKER_SLOTS: &'static [(SlotFilter, Vec2)] = &[
+        (
+            SlotFilter::Class(CardClass::Villager),
+            Vec2::new(-0.55, 0.15),
+        ),
+        (
+            SlotFilter::Class(CardClass::Villager),
+            Vec2::new(0.55, 0.15),
+        ),
+    ];
+    /// Two woodcutters with a spare axe slot below them.
+    pub const WOODS_SLOTS: &'static [(SlotFilter, Vec2)] = &[
+        (
+            SlotFilter::Class(CardClass::Villager),
+            Vec2::new(-0.55, 0.3),
+        ),
+        (SlotFilter::Class(CardClass::Villager), Vec2::new(0.55, 0.3)),
+        (SlotFilter::Type(CardType::Axe), Vec2::new(0.0, -0.9)),
+    ];
 
     pub fn grid_to_translation(grid_location: IVec2) -> Vec3 {
         (grid_location.as_vec2() * (Self::SIZE + Self::OFFSET)).extend(0.0)
@@ -84,69 +134,482 @@ impl Tile {
         Tile::TILE_SLOT_SIZE * Vec2::new(Tile::TILE_SLOT_ASPECT_RATIO, 1.0)
     }
 
-    pub fn has_slot(&self) -> bool {
+    pub const NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tile::Woods { .. } => "Woods",
+            Tile::Enemies { .. } => "Enemies",
+            Tile::Farm { .. } => "Farm",
+            Tile::Lake => "Lake",
+            Tile::LumberCamp { .. } => "Lumber Camp",
+            Tile::Cleared => "Cleared Land",
+            Tile::Market => "Market",
+        }
+    }
+
+    pub fn slot_layout(&self) -> &'static [(SlotFilter, Vec2)] {
+        match self {
+            Tile::Woods { .. } => Self::WOODS_SLOTS,
+            Tile::Farm { .. } | Tile::LumberCamp { .. } => Self::WORKER_SLOTS,
+            Tile::Enemies { .. } => EnemyCamp::ASSAULT_SLOTS,
+            Tile::Lake | Tile::Cleared | Tile::Market => &[],
+        }
+    }
+
+    /// The cards this tile hands out whenever its progress bar fills up.
+    pub fn production(&self) -> DropTable {
+        match self {
+            Tile::Woods { .. } => DropTable {
+                guaranteed: &[CardType::Log],
+                rare: &[(CardType::Berry, 0.1)],
+                ..DropTable::EMPTY
+            },
+            Tile::LumberCamp { .. } => DropTable {
+                guaranteed: &[CardType::Log],
+                rare: &[(CardType::Log, 0.25)],
+                ..DropTable::EMPTY
+            },
+            Tile::Farm { .. } => DropTable {
+                guaranteed: &[CardType::Berry],
+                rare: &[(CardType::Berry, 0.2)],
+                ..DropTable::EMPTY
+            },
+            // camps send out the waves of the wave director instead
+            Tile::Enemies { .. } | Tile::Lake | Tile::Cleared | Tile::Market => DropTable::EMPTY,
+        }
+    }
+
+    /// Production bonus this tile receives from a single orthogonal neighbour.
+    pub fn adjacency_bonus(&self, neighbour: &Tile) -> f32 {
+        match (self, neighbour) {
+            (
+                Tile::Woods { .. } | Tile::LumberCamp { .. },
+                Tile::Woods { .. } | Tile::LumberCamp { .. },
+            ) => 0.1,
+            (Tile::Farm { .. }, Tile::Lake) => 0.5,
+            (Tile::Farm { .. }, Tile::Farm { .. }) => 0.1,
+            (
+                Tile::Woods { .. } | Tile::Farm { .. } | Tile::LumberCamp { .. },
+                Tile::Enemies { .. },
+            ) => -0.25,
+            _ => 0.0,
+        }
+    }
+
+    /// How many production seconds pass per real second with the current workers and bonuses.
+    pub fn effective_rate(&self, tile_slots: &TileSlots, bonus: &TileBonus) -> f32 {
+        match self {
+            // villagers slotted into a camp are assaulting it, not working
+            Tile::Enemies { .. } => 0.0,
+            _ => {
+                let workers = tile_slots.filled(SlotFilter::Class(CardClass::Villager)) as f32;
+                // tools need someone to use them
+                if workers == 0.0 {
+                    return 0.0;
+                }
+                (workers + bonus.tools + bonus.skill).max(0.0) * bonus.multiplier
+            }
+        }
+    }
+
+    /// Where cards produced by this tile are placed.
+    pub fn spawn_point(tile_translation: Vec3) -> Vec3 {
+        Vec3::new(
+            tile_translation.x + Tile::SPAWN_OFFSET,
+            tile_translation.y,
+            0.0,
+        )
+    }
+
+    /// Size of the resource pool and how much of it regrows per second while idle.
+    pub fn resource_pool(&self) -> Option<(f32, f32)> {
+        match self {
+            Tile::Woods { .. } | Tile::LumberCamp { .. } => Some((8.0, 0.1)),
+            Tile::Farm { .. } => Some((6.0, 0.08)),
+            Tile::Enemies { .. } | Tile::Lake | Tile::Cleared | Tile::Market => None,
+        }
+    }
+
+    pub fn production_time(&self) -> f32 {
+        match self {
+            Tile::Woods { .. } => 15.0,
+            Tile::LumberCamp { .. } => 9.0,
+            Tile::Enemies { .. } | Tile::Farm { .. } => 20.0,
+            Tile::Lake | Tile::Cleared | Tile::Market => 0.0,
+        }
+    }
+
+    /// How many times slower than open ground it is to walk across, `None` if it can't be
+    /// crossed at all.
+    pub fn traversal_cost(&self) -> Option<f32> {
+        match self {
+            Tile::Lake => None,
+            Tile::Woods { .. } | Tile::LumberCamp { .. } => Some(1.5),
+            Tile::Enemies { .. } | Tile::Farm { .. } | Tile::Cleared | Tile::Market => Some(1.0),
+        }
+    }
+
+    /// The status effect kept up on villagers standing on this tile.
+    pub fn status_effect(&self) -> Option<StatusKind> {
+        match self {
+            Tile::Enemies { .. } => Some(StatusKind::Poison),
+            Tile::Farm { .. } => Some(StatusKind::Regeneration),
+            Tile::Woods { .. }
+            | Tile::Lake
+            | Tile::LumberCamp { .. }
+            | Tile::Cleared
+            | Tile::Market => None,
+        }
+    }
+
+    /// What this tile can be turned into by dropping a stack of resource cards on it.
+    pub fn upgrade(&self) -> Option<TileUpgrade> {
+        match self {
+            Tile::Woods { .. } => Some(TileUpgrade {
+                cost: CardType::Log,
+                amount: 5,
+                result: Tile::LumberCamp { progress_bar: None },
+                build_time: 20.0,
+            }),
+            Tile::Enemies { .. } => Some(TileUpgrade {
+                cost: CardType::Log,
+                amount: 5,
+                result: Tile::Cleared,
+                build_time: 30.0,
+            }),
+            Tile::Cleared => Some(TileUpgrade {
+                cost: CardType::Berry,
+                amount: 3,
+                result: Tile::Farm { progress_bar: None },
+                build_time: 15.0,
+            }),
+            Tile::Farm { .. } | Tile::Lake | Tile::LumberCamp { .. } | Tile::Market => None,
+        }
+    }
+}
+
+/// The health of an enemy camp. Villagers slotted into the camp damage it once none of its
+/// defenders are left standing on the tile, while the camp keeps calling in more.
+#[derive(Component)]
+pub struct EnemyCamp {
+    pub health: isize,
+    pub assault: Timer,
+    pub reinforcements: Timer,
+    pub health_bar: Entity,
+    pub label: Entity,
+}
+
+impl EnemyCamp {
+    pub const MAX_HEALTH: isize = 12;
+    pub const MAX_DEFENDERS: usize = 2;
+    pub const ASSAULT_SLOTS: &'static [(SlotFilter, Vec2)] = Tile::WORKER_SLOTS;
+    pub const LOOT: DropTable = DropTable {
+        guaranteed: &[
+            CardType::Log,
+            CardType::Log,
+            CardType::Berry,
+            CardType::Berry,
+        ],
+        rolls: 1,
+        weighted: &[
+            (Some(CardType::Sword), 1),
+            (Some(CardType::Shield), 1),
+            (Some(CardType::Axe), 1),
+            (Some(CardType::Hoe), 1),
+        ],
+        rare: &[],
+    };
+
+    pub fn new(health_bar: Entity, label: Entity) -> Self {
+        Self {
+            health: Self::MAX_HEALTH,
+            assault: Timer::from_seconds(1.0, TimerMode::Repeating),
+            reinforcements: Timer::from_seconds(3.0, TimerMode::Repeating),
+            health_bar,
+            label,
+        }
+    }
+}
+
+/// An enemy called in by a camp to defend it. Other enemies crossing the camp don't hold off the
+/// villagers assaulting it.
+#[derive(Component)]
+pub struct Defender {
+    pub camp: Entity,
+}
+
+#[derive(Clone, Copy)]
+pub struct TileUpgrade {
+    pub cost: CardType,
+    pub amount: usize,
+    pub result: Tile,
+    pub build_time: f32,
+}
+
+impl TileUpgrade {
+    /// Whether a stack holding these card types pays for the upgrade.
+    pub fn accepts(&self, stack: &[CardType]) -> bool {
+        stack.len() >= self.amount && stack.iter().all(|card_type| *card_type == self.cost)
+    }
+}
+
+/// An upgrade that has been paid for and is being built. The tile stops producing until the
+/// construction finishes and the tile is replaced.
+#[derive(Component)]
+pub struct TileConstruction {
+    pub result: Tile,
+    pub progress_bar: Entity,
+}
+
+/// Starts building the upgrade on a tile, its cost must already have been consumed.
+pub fn start_construction(commands: &mut Commands, tile_entity: Entity, upgrade: TileUpgrade) {
+    let mut progress_bar = None;
+    commands.entity(tile_entity).with_children(|parent| {
+        progress_bar = Some(
+            parent
+                .spawn(ProgressBarBundle {
+                    progress_bar: ProgressBar {
+                        current: 0.0,
+                        total: upgrade.build_time,
+                        width: 1.2,
+                        height: 0.15,
+                        padding: 0.05,
+                    },
+                    transform: Transform::from_xyz(0.0, -0.8, 0.0),
+                    ..default()
+                })
+                .id(),
+        );
+    });
+    commands.entity(tile_entity).insert(TileConstruction {
+        result: upgrade.result,
+        progress_bar: progress_bar.unwrap(),
+    });
+}
+
+/// Shows or hides a tile's progress bar, spawning it as a child of the tile when needed.
+pub fn toggle_progress_bar(
+    commands: &mut Commands,
+    tile_entity: Entity,
+    progress_bar: &mut Option<Entity>,
+    active: bool,
+    total: f32,
+) {
+    match (active, *progress_bar) {
+        (true, None) => {
+            commands.entity(tile_entity).with_children(|parent| {
+                *progress_bar = Some(
+                    parent
+                        .spawn(ProgressBarBundle {
+                            progress_bar: ProgressBar {
+                                current: 0.0,
+                                total,
+                                width: 0.85,
+                                height: 0.15,
+                                padding: 0.05,
+                            },
+                            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+                            ..default()
+                        })
+                        .id(),
+                );
+            });
+        }
+        (false, Some(bar)) => {
+            commands.entity(bar).despawn_recursive();
+            *progress_bar = None;
+        }
+        _ => {}
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlotFilter {
+    Class(CardClass),
+    Type(CardType),
+}
+
+impl SlotFilter {
+    pub fn accepts(&self, card: &Card) -> bool {
         match self {
-            Tile::Woods {
-                slotted_villager,
-                progress_bar,
-            } => true,
-            Tile::Enemies { progress_bar } => false,
+            // children don't work
+            SlotFilter::Class(class) => card.class() == *class && !card.is_juvenile(),
+            SlotFilter::Type(card_type) => card.card_type() == *card_type,
         }
     }
+}
+
+pub struct TileSlot {
+    pub filter: SlotFilter,
+    pub offset: Vec2,
+    pub card: Option<Entity>,
+    pub effect: Entity,
+}
+
+impl TileSlot {
+    pub fn contains(&self, tile_translation: Vec3, point: Vec3) -> bool {
+        let center = tile_translation.truncate() + self.offset;
+        let half_size = Tile::slot_size() / 2.0;
+        (point.truncate() - center).abs().cmplt(half_size).all()
+    }
+}
+
+/// The card slots of a tile, each with its own filter and highlight effect.
+#[derive(Component, Default, Deref, DerefMut)]
+pub struct TileSlots(pub Vec<TileSlot>);
+
+impl TileSlots {
+    pub fn spawn(
+        parent: &mut ChildBuilder,
+        tile_data: &TileData,
+        layout: &[(SlotFilter, Vec2)],
+    ) -> Self {
+        Self(
+            layout
+                .iter()
+                .map(|(filter, offset)| TileSlot {
+                    filter: *filter,
+                    offset: *offset,
+                    card: None,
+                    effect: parent
+                        .spawn(PbrBundle {
+                            material: tile_data.tile_slot_material.clone(),
+                            mesh: tile_data.tile_slot_mesh.clone(),
+                            transform: Transform::from_translation(offset.extend(0.001)),
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        })
+                        .id(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Number of occupied slots using the given filter.
+    pub fn filled(&self, filter: SlotFilter) -> usize {
+        self.iter()
+            .filter(|slot| slot.filter == filter && slot.card.is_some())
+            .count()
+    }
+
+    pub fn cards(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.iter().filter_map(|slot| slot.card)
+    }
+
+    pub fn card_offset(&self, card_entity: Entity) -> Option<Vec2> {
+        self.iter()
+            .find(|slot| slot.card == Some(card_entity))
+            .map(|slot| slot.offset)
+    }
 
     pub fn try_slotting_card(
         &mut self,
-        commands: &mut Commands,
-        tile_entity: Entity,
+        tile_translation: Vec3,
+        point: Vec3,
         card_entity: Entity,
         card: &Card,
     ) -> bool {
-        match self {
-            Tile::Woods {
-                slotted_villager,
-                progress_bar,
-            } => {
-                if slotted_villager.is_none() && card.class() == CardClass::Villager {
-                    *slotted_villager = Some(card_entity);
-                    let mut new_progress_bar = None;
-                    commands.entity(tile_entity).with_children(|parent| {
-                        new_progress_bar = Some(
-                            parent
-                                .spawn(ProgressBarBundle {
-                                    progress_bar: ProgressBar {
-                                        current: 0.0,
-                                        total: 15.0,
-                                        width: 0.85,
-                                        height: 0.15,
-                                        padding: 0.05,
-                                    },
-                                    transform: Transform::from_xyz(0.0, 1.0, 0.0),
-                                    ..default()
-                                })
-                                .id(),
-                        );
-                    });
-                    *progress_bar = new_progress_bar;
-                    true
-                } else {
-                    false
-                }
+        if let Some(slot) = self.iter_mut().find(|slot| {
+            slot.card.is_none()
+                && slot.filter.accepts(card)
+                && slot.contains(tile_translation, point)
+        }) {
+            slot.card = Some(card_entity);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Slots the card into the first free slot accepting it, wherever it was dropped.
+    pub fn try_slotting_anywhere(&mut self, card_entity: Entity, card: &Card) -> bool {
+        if let Some(slot) = self
+            .iter_mut()
+            .find(|slot| slot.card.is_none() && slot.filter.accepts(card))
+        {
+            slot.card = Some(card_entity);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn unslot_card(&mut self, card_entity: Entity) {
+        for slot in self.iter_mut() {
+            if slot.card == Some(card_entity) {
+                slot.card = None;
             }
-            _ => false,
         }
     }
 }
 
 #[derive(Component, Default, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
-pub struct TileGridLocation(IVec2);
+pub struct TileGridLocation(pub IVec2);
+
+/// The mesh showing the tile's ground.
+#[derive(Component)]
+pub struct TileSprite(pub Entity);
+
+/// A finite pool that drains as the tile produces and regrows while the tile is idle.
+#[derive(Component)]
+pub struct TileResource {
+    pub amount: f32,
+    pub max: f32,
+    pub regrowth: f32,
+    pub depleted: bool,
+    pub gauge: Entity,
+    pub label: Entity,
+}
+
+impl TileResource {
+    /// Share of the pool that has to regrow before a depleted tile produces again.
+    pub const RECOVERY: f32 = 0.25;
+
+    pub fn consume(&mut self) {
+        self.amount = (self.amount - 1.0).max(0.0);
+        if self.amount < 1.0 {
+            self.depleted = true;
+        }
+    }
+
+    pub fn regrow(&mut self, delta: f32) {
+        self.amount = (self.amount + self.regrowth * delta).min(self.max);
+        if self.depleted && self.amount >= (self.max * Self::RECOVERY).max(1.0) {
+            self.depleted = false;
+        }
+    }
+}
 
+/// Production multiplier a tile gets from its neighbours, and which neighbours contribute.
 #[derive(Component)]
-pub struct TileSlotEffect(Entity);
+pub struct TileBonus {
+    pub multiplier: f32,
+    pub sources: Vec<(IVec2, &'static str, f32)>,
+    /// Extra workers' worth of production from the tools of the slotted workers and in tool slots.
+    pub tools: f32,
+    /// Extra workers' worth of production from the traits and levels of the slotted workers.
+    pub skill: f32,
+    pub label: Option<Entity>,
+}
+
+impl Default for TileBonus {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            sources: Vec::new(),
+            tools: 0.0,
+            skill: 0.0,
+            label: None,
+        }
+    }
+}
 
 #[derive(Bundle, Default)]
 pub struct TileBundle {
     pub tile: Tile,
     pub tile_grid_location: TileGridLocation,
+    pub bonus: TileBonus,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
     pub visibility: Visibility,
@@ -155,11 +618,20 @@ pub struct TileBundle {
 
 #[derive(Resource)]
 pub struct TileData {
-    mesh: Handle<Mesh>,
-    woods_material: Handle<StandardMaterial>,
-    enemies_material: Handle<StandardMaterial>,
-    tile_slot_mesh: Handle<Mesh>,
-    tile_slot_material: Handle<StandardMaterial>,
+    pub mesh: Handle<Mesh>,
+    pub woods_material: Handle<StandardMaterial>,
+    pub woods_depleted_material: Handle<StandardMaterial>,
+    pub enemies_material: Handle<StandardMaterial>,
+    pub farm_material: Handle<StandardMaterial>,
+    pub farm_depleted_material: Handle<StandardMaterial>,
+    pub lake_material: Handle<StandardMaterial>,
+    pub lumber_camp_material: Handle<StandardMaterial>,
+    pub cleared_material: Handle<StandardMaterial>,
+    pub market_material: Handle<StandardMaterial>,
+    pub fog_material: Handle<StandardMaterial>,
+    pub tile_slot_mesh: Handle<Mesh>,
+    pub tile_slot_material: Handle<StandardMaterial>,
+    pub tile_slot_hover_material: Handle<StandardMaterial>,
 }
 
 impl FromWorld for TileData {
@@ -185,6 +657,14 @@ impl FromWorld for TileData {
                 alpha_mode: AlphaMode::Blend,
                 ..default()
             }),
+            woods_depleted_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile_woods.png")),
+                base_color: Color::rgb_u8(95, 85, 75),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
             enemies_material: materials.add(StandardMaterial {
                 base_color_texture: Some(asset_server.load("tile_woods.png")),
                 base_color: Color::rgb_u8(60, 60, 60),
@@ -193,6 +673,62 @@ impl FromWorld for TileData {
                 alpha_mode: AlphaMode::Blend,
                 ..default()
             }),
+            farm_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgb_u8(130, 120, 70),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            farm_depleted_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgb_u8(95, 85, 70),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            lake_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgb_u8(60, 80, 120),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            lumber_camp_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile_woods.png")),
+                base_color: Color::rgb_u8(125, 105, 75),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            cleared_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgb_u8(105, 95, 80),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            market_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgb_u8(140, 110, 60),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            fog_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile.png")),
+                base_color: Color::rgba_u8(40, 40, 50, 200),
+                unlit: true,
+                depth_bias: -10.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
             tile_slot_material: materials.add(StandardMaterial {
                 base_color_texture: Some(asset_server.load("tile_slot.png")),
                 base_color: Color::rgba_u8(255, 255, 255, 100),
@@ -201,6 +737,36 @@ impl FromWorld for TileData {
                 alpha_mode: AlphaMode::Blend,
                 ..default()
             }),
+            tile_slot_hover_material: materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load("tile_slot.png")),
+                base_color: Color::rgba_u8(255, 255, 255, 220),
+                unlit: true,
+                depth_bias: -9.0,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+        }
+    }
+}
+
+impl TileData {
+    pub fn tile_material(&self, tile: &Tile) -> Handle<StandardMaterial> {
+        match tile {
+            Tile::Woods { .. } => self.woods_material.clone(),
+            Tile::Enemies { .. } => self.enemies_material.clone(),
+            Tile::Farm { .. } => self.farm_material.clone(),
+            Tile::Lake => self.lake_material.clone(),
+            Tile::LumberCamp { .. } => self.lumber_camp_material.clone(),
+            Tile::Cleared => self.cleared_material.clone(),
+            Tile::Market => self.market_material.clone(),
+        }
+    }
+
+    pub fn depleted_material(&self, tile: &Tile) -> Handle<StandardMaterial> {
+        match tile {
+            Tile::Woods { .. } | Tile::LumberCamp { .. } => self.woods_depleted_material.clone(),
+            Tile::Farm { .. } => self.farm_depleted_material.clone(),
+            _ => self.tile_material(tile),
         }
     }
 }
@@ -213,69 +779,142 @@ fn on_spawn_tile(
     asset_server: Res<AssetServer>,
     tile_data: Res<TileData>,
     mut tile_grid: ResMut<TileGrid>,
-    mut tiles: Query<(Entity, &mut Tile, &TileGridLocation, &mut Transform), Added<Tile>>,
+    mut tiles: Query<
+        (
+            Entity,
+            &mut Tile,
+            &TileGridLocation,
+            &mut Transform,
+            &mut TileBonus,
+        ),
+        Added<Tile>,
+    >,
 ) {
-    for (entity, mut tile, location, mut transform) in &mut tiles {
+    for (entity, mut tile, location, mut transform, mut bonus) in &mut tiles {
         tile_grid.insert(location.0, entity);
         transform.translation = Tile::grid_to_translation(location.0);
-        let mut tile_slot = None;
-        match &mut *tile {
-            Tile::Woods {
-                slotted_villager,
-                progress_bar,
-            } => {
-                commands.entity(entity).with_children(|parent| {
-                    parent.spawn(PbrBundle {
-                        material: tile_data.woods_material.clone(),
-                        mesh: tile_data.mesh.clone(),
-                        ..default()
-                    });
-                });
-            }
-            Tile::Enemies { progress_bar } => {
-                commands.entity(entity).with_children(|parent| {
-                    parent.spawn(PbrBundle {
-                        material: tile_data.enemies_material.clone(),
+        let layout = tile.slot_layout();
+        let production_time = tile.production_time();
+        let resource_pool = tile.resource_pool();
+        let material = tile_data.tile_material(&tile);
+        let mut sprite = None;
+        commands.entity(entity).with_children(|parent| {
+            sprite = Some(
+                parent
+                    .spawn(PbrBundle {
+                        material,
                         mesh: tile_data.mesh.clone(),
                         ..default()
-                    });
-
-                    *progress_bar = Some(
-                        parent
-                            .spawn(ProgressBarBundle {
-                                progress_bar: ProgressBar {
-                                    current: 0.0,
-                                    total: 20.0,
-                                    width: 1.0,
-                                    height: 0.15,
-                                    padding: 0.05,
-                                },
-                                transform: Transform::from_xyz(0.0, 1.0, 0.0),
-                                ..default()
-                            })
-                            .id(),
-                    );
-                });
-            }
+                    })
+                    .id(),
+            );
+        });
+        commands.entity(entity).insert(TileSprite(sprite.unwrap()));
+
+        if let Tile::Enemies { progress_bar } = &mut *tile {
+            commands.entity(entity).with_children(|parent| {
+                *progress_bar = Some(
+                    parent
+                        .spawn(ProgressBarBundle {
+                            progress_bar: ProgressBar {
+                                current: 0.0,
+                                total: production_time,
+                                width: 1.0,
+                                height: 0.15,
+                                padding: 0.05,
+                            },
+                            transform: Transform::from_xyz(0.0, 1.0, 0.0),
+                            ..default()
+                        })
+                        .id(),
+                );
+            });
+
+            let mut health_bar = None;
+            commands.entity(entity).with_children(|parent| {
+                health_bar = Some(
+                    parent
+                        .spawn(ProgressBarBundle {
+                            progress_bar: ProgressBar {
+                                current: EnemyCamp::MAX_HEALTH as f32,
+                                total: EnemyCamp::MAX_HEALTH as f32,
+                                width: 1.2,
+                                height: 0.1,
+                                padding: 0.03,
+                            },
+                            transform: Transform::from_xyz(0.0, -1.3, 0.0),
+                            ..default()
+                        })
+                        .id(),
+                );
+            });
+            let label = commands
+                .spawn(WorldLabelBundle::new(
+                    entity,
+                    Vec3::new(0.0, -1.1, 0.0),
+                    12.0,
+                    Color::rgb(1.0, 0.6, 0.6),
+                ))
+                .id();
+            commands
+                .entity(entity)
+                .insert(EnemyCamp::new(health_bar.unwrap(), label));
         }
 
-        if tile.has_slot() {
+        if let Some((max, regrowth)) = resource_pool {
+            let mut gauge = None;
             commands.entity(entity).with_children(|parent| {
-                tile_slot = Some(
+                gauge = Some(
                     parent
-                        .spawn(PbrBundle {
-                            material: tile_data.tile_slot_material.clone(),
-                            mesh: tile_data.tile_slot_mesh.clone(),
-                            transform: Transform::from_xyz(0.0, 0.0, 0.001),
-                            visibility: Visibility::Hidden,
+                        .spawn(ProgressBarBundle {
+                            progress_bar: ProgressBar {
+                                current: max,
+                                total: max,
+                                width: 1.2,
+                                height: 0.1,
+                                padding: 0.03,
+                            },
+                            transform: Transform::from_xyz(-0.55, -1.3, 0.0),
                             ..default()
                         })
                         .id(),
                 );
             });
+            let label = commands
+                .spawn(WorldLabelBundle::new(
+                    entity,
+                    Vec3::new(-0.55, -1.1, 0.0),
+                    12.0,
+                    Color::WHITE,
+                ))
+                .id();
+            commands.entity(entity).insert(TileResource {
+                amount: max,
+                max,
+                regrowth,
+                depleted: false,
+                gauge: gauge.unwrap(),
+                label,
+            });
         }
-        if let Some(tile_slot) = tile_slot {
-            commands.entity(entity).insert(TileSlotEffect(tile_slot));
+
+        let mut tile_slots = TileSlots::default();
+        commands.entity(entity).with_children(|parent| {
+            tile_slots = TileSlots::spawn(parent, &tile_data, layout);
+        });
+        commands.entity(entity).insert(tile_slots);
+
+        if !layout.is_empty() {
+            bonus.label = Some(
+                commands
+                    .spawn(WorldLabelBundle::new(
+                        entity,
+                        Vec3::new(0.0, 1.25, 0.0),
+                        14.0,
+                        Color::WHITE,
+                    ))
+                    .id(),
+            );
         }
     }
 }
@@ -302,101 +941,605 @@ pub struct HoveredTile(pub Option<Entity>);
 pub fn hover_tile(
     hover_point: Res<HoverPoint>,
     tile_grid: Res<TileGrid>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+    fog_grid: Res<FogGrid>,
+    tile_data: Res<TileData>,
     mut hovered_tile: ResMut<HoveredTile>,
     selected_card: Res<SelectedCard>,
-    mut visibilities: Query<&mut Visibility>,
-    tile_slots: Query<&TileSlotEffect>,
-    tiles: Query<(&Tile, &TileSlotEffect)>,
+    cards: Query<&Card>,
+    tiles: Query<(Entity, &TileSlots, &Transform)>,
+    mut slot_effects: Query<(&mut Visibility, &mut Handle<StandardMaterial>)>,
 ) {
-    if let Some(tile_entity) = hovered_tile.0 {
-        if let Ok(tile_slot) = tile_slots.get(tile_entity) {
-            let mut visibility = visibilities.get_mut(tile_slot.0).unwrap();
-            *visibility = Visibility::Hidden;
+    let selected = if let SelectedCard::Some(entity) = *selected_card {
+        cards.get(entity).ok()
+    } else {
+        None
+    };
+
+    if selected.is_some() {
+        if let HoverPoint::Some(point) = *hover_point {
+            let location = Tile::translation_to_grid(point);
+            hovered_tile.0 = tile_grid
+                .get(&location)
+                .or_else(|| fog_grid.get(&location))
+                .copied();
+        } else {
+            hovered_tile.0 = None;
         }
     }
-    for (tile, tile_slot) in tiles.iter() {
-        match tile {
-            Tile::Woods {
-                slotted_villager, ..
-            } => {
-                let mut visibility = visibilities.get_mut(tile_slot.0).unwrap();
-                *visibility = if slotted_villager.is_some() {
+
+    // occupied slots are always shown, free slots only when the held card fits into them
+    for (entity, tile_slots, transform) in &tiles {
+        let hovered = selected.is_some() && hovered_tile.0 == Some(entity);
+        for slot in tile_slots.iter() {
+            if let Ok((mut visibility, mut material)) = slot_effects.get_mut(slot.effect) {
+                let candidate = hovered
+                    && slot.card.is_none()
+                    && selected.is_some_and(|card| slot.filter.accepts(card));
+                let under_cursor = candidate
+                    && matches!(*hover_point, HoverPoint::Some(point) if slot.contains(transform.translation, point));
+                *visibility = if slot.card.is_some() || candidate {
                     Visibility::Visible
                 } else {
                     Visibility::Hidden
                 };
+                *material = if under_cursor {
+                    tile_data.tile_slot_hover_material.clone()
+                } else {
+                    tile_data.tile_slot_material.clone()
+                };
             }
-            _ => {}
         }
     }
+}
 
-    if let SelectedCard::Some(_) = *selected_card {
-        if let HoverPoint::Some(point) = *hover_point {
-            let location = Tile::translation_to_grid(point);
-            if let Some(tile_entity) = tile_grid.get(&location) {
-                hovered_tile.0 = Some(*tile_entity);
-                let tile_slot = tile_slots.get(*tile_entity).unwrap().0;
-                let mut visibility = visibilities.get_mut(tile_slot).unwrap();
-                *visibility = Visibility::Visible;
-            } else {
-                hovered_tile.0 = None;
+/// Frees slots whose card no longer exists or has been moved elsewhere. Cards slotted into a tile
+/// that disappears are released as well, so a tile can be despawned without emptying its slots.
+pub fn clean_tile_slots(mut tiles: Query<(Entity, &mut TileSlots)>, cards: Query<&Card>) {
+    for (tile_entity, mut tile_slots) in &mut tiles {
+        for slot in tile_slots.iter_mut() {
+            if let Some(card_entity) = slot.card {
+                let still_slotted = cards
+                    .get(card_entity)
+                    .is_ok_and(|card| card.slotted_in_tile == Some(tile_entity));
+                if !still_slotted {
+                    slot.card = None;
+                }
             }
-        } else {
-            hovered_tile.0 = None;
         }
     }
 }
 
-fn evaluate_tiles(
+#[allow(clippy::type_complexity)]
+pub fn evaluate_tiles(
     mut commands: Commands,
     time: Res<Time>,
-    mut tiles: Query<(&mut Tile, &Transform)>,
+    mut rng: ResMut<GameRng>,
+    mut tiles: Query<(
+        Entity,
+        &mut Tile,
+        &TileSlots,
+        &TileBonus,
+        &Transform,
+        Option<&mut TileResource>,
+        Has<TileConstruction>,
+    )>,
     mut progress_bars: Query<&mut ProgressBar>,
+    mut cards: Query<&mut Card>,
 ) {
-    for (mut tile, transform) in &mut tiles {
+    for (entity, mut tile, tile_slots, bonus, transform, mut resource, constructing) in &mut tiles {
+        let production_time = tile.production_time();
+        let production = tile.production();
+        // every filled worker slot adds to the production speed, scaled by the neighbours
+        let rate = if constructing {
+            0.0
+        } else {
+            tile.effective_rate(tile_slots, bonus)
+        };
         match &mut *tile {
-            Tile::Woods {
-                slotted_villager,
-                progress_bar,
-            } => {
-                if let Some(bar_entity) = *progress_bar {
+            Tile::Woods { progress_bar }
+            | Tile::Farm { progress_bar }
+            | Tile::LumberCamp { progress_bar } => {
+                toggle_progress_bar(
+                    &mut commands,
+                    entity,
+                    progress_bar,
+                    rate > 0.0,
+                    production_time,
+                );
+                // a depleted tile keeps its workers but pauses until the pool has regrown
+                let depleted = resource.as_ref().is_some_and(|resource| resource.depleted);
+                if let (Some(bar_entity), false) = (*progress_bar, depleted) {
                     if let Ok(mut bar) = progress_bars.get_mut(bar_entity) {
-                        bar.add(time.delta_seconds());
+                        bar.add(time.delta_seconds() * rate);
                         if bar.finished() {
-                            commands.spawn(CardBundle {
-                                card: Card::from(CardType::Log),
-                                transform: Transform::from_xyz(
-                                    transform.translation.x + Tile::SPAWN_OFFSET,
-                                    transform.translation.y,
-                                    0.0,
-                                ),
-                                ..default()
-                            });
+                            // products land on the same spot and pile up
+                            for product in production.roll(&mut rng) {
+                                commands.spawn(CardBundle {
+                                    card: Card::from(product),
+                                    transform: Transform::from_translation(Tile::spawn_point(
+                                        transform.translation,
+                                    )),
+                                    ..default()
+                                });
+                            }
                             bar.reset();
+                            reward_workers(&tile, tile_slots, &mut cards);
+                            if let Some(resource) = resource.as_mut() {
+                                resource.consume();
+                            }
                         }
                     }
                 }
+                if let Some(resource) = resource.as_mut() {
+                    if rate == 0.0 || resource.depleted {
+                        resource.regrow(time.delta_seconds());
+                    }
+                }
             }
-            Tile::Enemies { progress_bar } => {
-                if let Some(bar_entity) = *progress_bar {
-                    if let Ok(mut bar) = progress_bars.get_mut(bar_entity) {
-                        bar.add(time.delta_seconds());
-                        if bar.finished() {
-                            commands.spawn(CardBundle {
-                                card: Card::from(CardType::Goblin),
-                                transform: Transform::from_xyz(
-                                    transform.translation.x,
-                                    transform.translation.y,
-                                    0.0,
-                                ),
-                                ..default()
-                            });
-                            bar.reset();
-                        }
+            // camps spawn their goblins in waves, see `WaveDirector`
+            Tile::Enemies { .. } => {}
+            Tile::Lake | Tile::Cleared | Tile::Market => {}
+        }
+    }
+}
+
+fn assault_camps(
+    mut commands: Commands,
+    time: Res<Time>,
+    curve: Res<DifficultyCurve>,
+    director: Res<WaveDirector>,
+    mut rng: ResMut<GameRng>,
+    mut camps: Query<(
+        Entity,
+        &mut EnemyCamp,
+        &TileSlots,
+        &TileGridLocation,
+        &Transform,
+    )>,
+    cards: Query<(&Card, &Transform)>,
+    defenders: Query<(&Defender, &Transform)>,
+) {
+    for (entity, mut camp, tile_slots, location, transform) in &mut camps {
+        let assaulters: Vec<&Card> = tile_slots
+            .cards()
+            .filter_map(|card_entity| cards.get(card_entity).ok())
+            .map(|(card, _)| card)
+            .collect();
+        if assaulters.is_empty() {
+            camp.assault.reset();
+            camp.reinforcements.reset();
+            continue;
+        }
+
+        let half_size = Tile::SIZE / 2.0;
+        let defenders = defenders
+            .iter()
+            .filter(|(defender, defender_transform)| {
+                defender.camp == entity
+                    && (defender_transform.translation - transform.translation)
+                        .truncate()
+                        .abs()
+                        .cmplt(half_size)
+                        .all()
+            })
+            .count();
+
+        // the camp calls in defenders while it is under attack, as strong as the waves are
+        if camp.reinforcements.tick(time.delta()).just_finished()
+            && defenders < EnemyCamp::MAX_DEFENDERS
+        {
+            let villagers = cards
+                .iter()
+                .filter(|(card, _)| card.class() == CardClass::Villager)
+                .count();
+            let threat = curve.threat(director.elapsed, director.days, villagers);
+            commands.spawn((
+                CardBundle {
+                    card: curve.defender(threat, &mut rng),
+                    transform: Transform::from_xyz(
+                        transform.translation.x,
+                        transform.translation.y,
+                        0.0,
+                    ),
+                    ..default()
+                },
+                Defender { camp: entity },
+            ));
+        }
+
+        // villagers busy fighting defenders don't damage the camp
+        if camp.assault.tick(time.delta()).just_finished() && defenders == 0 {
+            let damage: isize = assaulters
+                .iter()
+                .filter(|card| card.combat_state.is_none())
+                .map(|card| card.combat_stats().damage as isize)
+                .sum();
+            camp.health = (camp.health - damage).max(0);
+        }
+
+        if camp.health == 0 {
+            commands.entity(entity).despawn_recursive();
+            commands.spawn(TileBundle {
+                tile: Tile::Cleared,
+                tile_grid_location: *location,
+                ..default()
+            });
+            let loot = EnemyCamp::LOOT.roll(&mut rng);
+            spawn_drops(
+                &mut commands,
+                &loot,
+                transform.translation,
+                transform.translation,
+            );
+        }
+    }
+}
+
+fn show_camp_health(
+    camps: Query<&EnemyCamp>,
+    mut progress_bars: Query<&mut ProgressBar>,
+    mut texts: Query<&mut Text>,
+) {
+    for camp in &camps {
+        if let Ok(mut bar) = progress_bars.get_mut(camp.health_bar) {
+            bar.current = camp.health as f32;
+        }
+        if let Ok(mut text) = texts.get_mut(camp.label) {
+            let value = format!("Camp {}/{}", camp.health, EnemyCamp::MAX_HEALTH);
+            set_text(&mut text, &value);
+        }
+    }
+}
+
+/// Advances paid upgrades and replaces the tile once its construction is done.
+fn construct_tiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    tiles: Query<(Entity, &TileConstruction, &TileGridLocation)>,
+    mut progress_bars: Query<&mut ProgressBar>,
+) {
+    for (entity, construction, location) in &tiles {
+        if let Ok(mut bar) = progress_bars.get_mut(construction.progress_bar) {
+            bar.add(time.delta_seconds());
+            if bar.finished() {
+                commands.entity(entity).despawn_recursive();
+                commands.spawn(TileBundle {
+                    tile: construction.result,
+                    tile_grid_location: *location,
+                    ..default()
+                });
+            }
+        }
+    }
+}
+
+fn compute_adjacency(
+    tile_grid: Res<TileGrid>,
+    neighbours: Query<&Tile>,
+    mut tiles: Query<(&Tile, &TileGridLocation, &mut TileBonus)>,
+) {
+    if !tile_grid.is_changed() {
+        return;
+    }
+
+    for (tile, location, mut bonus) in &mut tiles {
+        bonus.sources.clear();
+        for offset in Tile::NEIGHBOURS {
+            let neighbour_location = location.0 + offset;
+            if let Some(neighbour) = tile_grid
+                .get(&neighbour_location)
+                .and_then(|entity| neighbours.get(*entity).ok())
+            {
+                let amount = tile.adjacency_bonus(neighbour);
+                if amount != 0.0 {
+                    bonus
+                        .sources
+                        .push((neighbour_location, neighbour.name(), amount));
+                }
+            }
+        }
+        bonus.multiplier = (1.0 + bonus.sources.iter().map(|(_, _, a)| a).sum::<f32>()).max(0.0);
+    }
+}
+
+fn label_tile_rates(tiles: Query<(&Tile, &TileSlots, &TileBonus)>, mut texts: Query<&mut Text>) {
+    for (tile, tile_slots, bonus) in &tiles {
+        if let Some(Ok(mut text)) = bonus.label.map(|label| texts.get_mut(label)) {
+            let rate = tile.effective_rate(tile_slots, bonus);
+            let value = if rate > 0.0 {
+                format!(
+                    "x{:.2}  {:.1}s",
+                    bonus.multiplier,
+                    tile.production_time() / rate
+                )
+            } else if bonus.multiplier != 1.0 {
+                format!("x{:.2}", bonus.multiplier)
+            } else {
+                String::new()
+            };
+            let color = if bonus.multiplier > 1.0 {
+                Color::rgb(0.6, 1.0, 0.6)
+            } else if bonus.multiplier < 1.0 {
+                Color::rgb(1.0, 0.6, 0.6)
+            } else {
+                Color::WHITE
+            };
+            set_text(&mut text, &value);
+            set_text_color(&mut text, color);
+        }
+    }
+}
+
+fn show_tile_resources(
+    tile_data: Res<TileData>,
+    tiles: Query<(&Tile, &TileResource, &TileSprite)>,
+    mut materials: Query<&mut Handle<StandardMaterial>>,
+    mut progress_bars: Query<&mut ProgressBar>,
+    mut texts: Query<&mut Text>,
+) {
+    for (tile, resource, sprite) in &tiles {
+        if let Ok(mut bar) = progress_bars.get_mut(resource.gauge) {
+            bar.current = resource.amount;
+        }
+        if let Ok(mut material) = materials.get_mut(sprite.0) {
+            let target = if resource.depleted {
+                tile_data.depleted_material(tile)
+            } else {
+                tile_data.tile_material(tile)
+            };
+            if *material != target {
+                *material = target;
+            }
+        }
+        if let Ok(mut text) = texts.get_mut(resource.label) {
+            let value = if resource.depleted {
+                "Depleted".to_string()
+            } else {
+                format!("{:.0}/{:.0} left", resource.amount.floor(), resource.max)
+            };
+            set_text(&mut text, &value);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TileInfoPanel;
+
+fn spawn_tile_info_panel(mut commands: Commands) {
+    commands
+        .spawn(TextBundle {
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            visibility: Visibility::Hidden,
+            ..hud_text(
+                16.0,
+                Color::WHITE,
+                Style {
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+            )
+        })
+        .insert(TileInfoPanel);
+}
+
+/// Explains which neighbours contribute to the bonus of the tile under the cursor, and what it
+/// can be upgraded into.
+fn show_tile_info(
+    day_cycle: Res<DayCycle>,
+    hover_point: Res<HoverPoint>,
+    selected_card: Res<SelectedCard>,
+    tile_grid: Res<TileGrid>,
+    tiles: Query<(&Tile, &TileBonus, Option<&TileConstruction>)>,
+    mut panels: Query<(&mut Text, &mut Visibility), With<TileInfoPanel>>,
+) {
+    let hovered = match (*selected_card, &*hover_point) {
+        (SelectedCard::None, HoverPoint::Some(point)) => tile_grid
+            .get(&Tile::translation_to_grid(*point))
+            .and_then(|entity| tiles.get(*entity).ok()),
+        _ => None,
+    };
+
+    for (mut text, mut visibility) in &mut panels {
+        if let Some((tile, bonus, construction)) = hovered {
+            let mut value = format!("{}  x{:.2}", tile.name(), bonus.multiplier);
+            for (location, name, amount) in &bonus.sources {
+                value.push_str(&format!(
+                    "\n{:+.0}% from {} ({}, {})",
+                    amount * 100.0,
+                    name,
+                    location.x,
+                    location.y
+                ));
+            }
+            if let Some(construction) = construction {
+                value.push_str(&format!("\nBuilding {}", construction.result.name()));
+            } else if *tile == Tile::Market {
+                value.push_str("\nDrop cards to sell them for coins");
+                for theme in PackTheme::ALL {
+                    if theme.unlock_day() <= day_cycle.day {
+                        value.push_str(&format!(
+                            "\nDrop {} Coin to buy a {} pack",
+                            theme.price(),
+                            theme.name()
+                        ));
                     }
                 }
+            } else if let Some(upgrade) = tile.upgrade() {
+                value.push_str(&format!(
+                    "\nDrop {} {:?} to build {} ({:.0}s)",
+                    upgrade.amount,
+                    upgrade.cost,
+                    upgrade.result.name(),
+                    upgrade.build_time
+                ));
+            }
+            set_text(&mut text, &value);
+            *visibility = Visibility::Inherited;
+        } else {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        card::{CardDied, StackRoots},
+        status::{apply_tile_effects, tick_status_effects, StatusKind},
+    };
+
+    fn assault_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<GameRng>()
+            .init_resource::<DifficultyCurve>()
+            .init_resource::<WaveDirector>()
+            .init_resource::<TileGrid>()
+            .init_resource::<StackRoots>()
+            .init_resource::<SelectedCard>()
+            .add_event::<CardDied>()
+            .add_systems(Update, assault_camps);
+        app
+    }
+
+    /// Spawns an enemy camp with a villager in each of its assault slots, and returns the camp and
+    /// the villagers.
+    fn camp_under_assault(app: &mut App) -> (Entity, Vec<Entity>) {
+        let location = IVec2::new(0, 2);
+        let translation = Tile::grid_to_translation(location);
+        let camp = app.world.spawn_empty().id();
+        let villagers: Vec<_> = EnemyCamp::ASSAULT_SLOTS
+            .iter()
+            .map(|_| {
+                let mut card = Card::from(CardType::Villager);
+                card.slotted_in_tile = Some(camp);
+                app.world
+                    .spawn((card, Transform::from_translation(translation)))
+                    .id()
+            })
+            .collect();
+        let slots = EnemyCamp::ASSAULT_SLOTS
+            .iter()
+            .zip(&villagers)
+            .map(|((filter, offset), villager)| TileSlot {
+                filter: *filter,
+                offset: *offset,
+                card: Some(*villager),
+                effect: Entity::PLACEHOLDER,
+            })
+            .collect();
+        app.world.entity_mut(camp).insert((
+            Tile::Enemies { progress_bar: None },
+            EnemyCamp::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER),
+            TileSlots(slots),
+            TileGridLocation(location),
+            Transform::from_translation(translation),
+        ));
+        app.world.resource_mut::<TileGrid>().insert(location, camp);
+        (camp, villagers)
+    }
+
+    /// Runs the assault for 30 seconds. With `hold_out` the camp's defenders stay, otherwise they
+    /// fall as soon as they arrive.
+    fn run_assault(app: &mut App, hold_out: bool) {
+        for _ in 0..300 {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(100));
+            app.update();
+            if hold_out {
+                continue;
+            }
+            let defenders: Vec<_> = app
+                .world
+                .query_filtered::<Entity, With<Defender>>()
+                .iter(&app.world)
+                .collect();
+            for defender in defenders {
+                app.world.despawn(defender);
             }
         }
     }
+
+    fn camp_cleared(app: &mut App) -> bool {
+        app.world
+            .query::<&Tile>()
+            .iter(&app.world)
+            .any(|tile| *tile == Tile::Cleared)
+    }
+
+    #[test]
+    fn villagers_clear_an_undefended_camp() {
+        let mut app = assault_app();
+        let (_, villagers) = camp_under_assault(&mut app);
+
+        run_assault(&mut app, false);
+
+        assert!(camp_cleared(&mut app), "the camp should have fallen");
+        for villager in villagers {
+            let card = app.world.get::<Card>(villager).unwrap();
+            assert_eq!(card.info.stats.health, card.info.stats.max_health as isize);
+        }
+    }
+
+    #[test]
+    fn defenders_on_the_tile_protect_the_camp() {
+        let mut app = assault_app();
+        let (camp, _) = camp_under_assault(&mut app);
+        let translation = app.world.get::<Transform>(camp).unwrap().translation;
+        app.world.spawn((
+            Card::from(CardType::Goblin),
+            Transform::from_translation(translation),
+            Defender { camp },
+        ));
+
+        run_assault(&mut app, true);
+
+        let camp = app.world.get::<EnemyCamp>(camp).unwrap();
+        assert_eq!(camp.health, EnemyCamp::MAX_HEALTH);
+    }
+
+    #[test]
+    fn enemies_passing_through_do_not_defend_the_camp() {
+        let mut app = assault_app();
+        let (camp, _) = camp_under_assault(&mut app);
+        let translation = app.world.get::<Transform>(camp).unwrap().translation;
+        let passer = app
+            .world
+            .spawn((
+                Card::from(CardType::Thief),
+                Transform::from_translation(translation),
+            ))
+            .id();
+
+        run_assault(&mut app, false);
+
+        assert!(camp_cleared(&mut app), "the camp should have fallen");
+        assert!(app.world.get::<Card>(passer).is_some());
+    }
+
+    #[test]
+    fn villagers_assaulting_a_camp_are_not_poisoned() {
+        let mut app = assault_app();
+        app.add_systems(
+            Update,
+            (
+                apply_tile_effects,
+                tick_status_effects.after(apply_tile_effects),
+            ),
+        );
+        let (_, villagers) = camp_under_assault(&mut app);
+
+        run_assault(&mut app, false);
+
+        assert!(camp_cleared(&mut app), "the camp should have fallen");
+        for villager in villagers {
+            let card = app.world.get::<Card>(villager).unwrap();
+            assert!(card.effects.get(StatusKind::Poison).is_none());
+            assert_eq!(card.info.stats.health, card.info.stats.max_health as isize);
+        }
+    }
 }
@@ -0,0 +1,664 @@
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    card::{
+        kill_card, AttackOutcome, Card, CardDied, CardStats, CardType, SelectedCard, StackRoots,
+    },
+    combat::CombatHit,
+    day_cycle::EndOfDay,
+    equipment::StatBonus,
+    rng::GameRng,
+    tile::{Tile, TileBonus, TileSlots},
+};
+
+pub struct VillagerPlugin;
+
+impl Plugin for VillagerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            name_villagers.after(crate::game::card::on_spawn_card),
+        )
+        .add_systems(
+            Update,
+            train_fighters
+                .after(crate::game::card::combat)
+                .after(crate::game::buildings::man_watchtowers),
+        )
+        .add_systems(Update, age_villagers)
+        .add_systems(
+            Update,
+            count_skill
+                .after(crate::game::tile::clean_tile_slots)
+                .before(crate::game::tile::evaluate_tiles),
+        );
+        // the web build has no file system to save to
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Update, (save_villagers, load_villagers));
+    }
+}
+
+/// Something a villager is born with that sets it apart from the others.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Trait {
+    Strong,
+    FastWorker,
+    Lazy,
+}
+
+impl Trait {
+    pub const ALL: [Trait; 3] = [Trait::Strong, Trait::FastWorker, Trait::Lazy];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trait::Strong => "Strong",
+            Trait::FastWorker => "Fast worker",
+            Trait::Lazy => "Lazy",
+        }
+    }
+
+    /// Extra workers' worth of production the villager gives when slotted into a tile.
+    pub fn work_bonus(&self) -> f32 {
+        match self {
+            Trait::Strong => 0.0,
+            Trait::FastWorker => 0.5,
+            Trait::Lazy => -0.5,
+        }
+    }
+
+    fn add_to(&self, stats: &mut CardStats) {
+        if *self == Trait::Strong {
+            stats.damage += 1;
+        }
+    }
+
+    fn conflicts_with(&self, other: Trait) -> bool {
+        matches!(
+            (self, other),
+            (Trait::FastWorker, Trait::Lazy) | (Trait::Lazy, Trait::FastWorker)
+        )
+    }
+}
+
+/// What a villager does for a living. It takes up the profession of the last tool it equipped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Profession {
+    Lumberjack,
+    Soldier,
+    Farmer,
+}
+
+impl Profession {
+    pub fn of_tool(item: CardType) -> Option<Self> {
+        match item {
+            CardType::Axe => Some(Profession::Lumberjack),
+            CardType::Sword => Some(Profession::Soldier),
+            CardType::Hoe => Some(Profession::Farmer),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profession::Lumberjack => "Lumberjack",
+            Profession::Soldier => "Soldier",
+            Profession::Farmer => "Farmer",
+        }
+    }
+
+    /// Extra workers' worth of production the villager gives when working the given tile. Like
+    /// the combat bonus, it takes the place of what the tool of the trade gives.
+    pub fn work_bonus(&self, tile: &Tile) -> f32 {
+        match (self, tile) {
+            (Profession::Lumberjack, Tile::Woods { .. } | Tile::LumberCamp { .. }) => 0.75,
+            (Profession::Farmer, Tile::Farm { .. }) => 0.75,
+            _ => 0.0,
+        }
+    }
+
+    /// What the profession adds to the stats the villager fights with. It takes the place of
+    /// what the tool of the trade gives, since a villager wields it better once it took up the
+    /// trade.
+    pub fn combat_bonus(&self) -> StatBonus {
+        match self {
+            Profession::Soldier => StatBonus {
+                damage: 1,
+                crit_chance: 0.1,
+                ..default()
+            },
+            Profession::Lumberjack => StatBonus::of(CardType::Axe),
+            Profession::Farmer => StatBonus::of(CardType::Hoe),
+        }
+    }
+}
+
+const NAME_STARTS: [&str; 16] = [
+    "Al", "Bren", "Cor", "Da", "El", "Fen", "Gil", "Har", "Is", "Jor", "Ka", "Lin", "Mar", "Nor",
+    "Ro", "Wen",
+];
+const NAME_ENDS: [&str; 10] = [
+    "a", "an", "ric", "wyn", "os", "ell", "ia", "mund", "o", "ey",
+];
+
+/// Who a villager is: its name, its traits, its age and how far it has come. It is kept with the
+/// villager when the game is saved.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Identity {
+    pub name: String,
+    pub traits: Vec<Trait>,
+    /// In days.
+    pub age: u32,
+    pub profession: Option<Profession>,
+    pub level: u32,
+    /// Experience gathered towards the next level.
+    pub experience: u32,
+}
+
+impl Identity {
+    pub const MAX_LEVEL: u32 = 5;
+    /// Experience for a hit in a fight, and on top of it for the final blow.
+    pub const HIT_EXPERIENCE: u32 = 1;
+    pub const KILL_EXPERIENCE: u32 = 3;
+    /// Experience for every batch of logs a woodcutter helps produce.
+    pub const WOODCUTTING_EXPERIENCE: u32 = 2;
+    /// Extra workers' worth of production per level above the first.
+    pub const WORK_BONUS_PER_LEVEL: f32 = 0.1;
+    /// What every level above the first adds to the fighting stats.
+    pub const ATTACK_SPEED_PER_LEVEL: f32 = 0.1;
+    pub const DODGE_PER_LEVEL: f32 = 0.02;
+    /// Age at which children start to work.
+    pub const ADULT_AGE: u32 = 2;
+    /// Age from which a villager may die of old age at the end of any day.
+    pub const ELDER_AGE: u32 = 8;
+    pub const OLD_AGE_DEATH_CHANCE: f32 = 0.25;
+    /// Nobody lives to see this age.
+    pub const MAX_AGE: u32 = 12;
+
+    /// A new villager of the given age with a made up name and one or two traits.
+    pub fn generate(rng: &mut GameRng, age: u32) -> Self {
+        let name = format!(
+            "{}{}",
+            NAME_STARTS.choose(&mut **rng).unwrap(),
+            NAME_ENDS.choose(&mut **rng).unwrap()
+        );
+        let mut traits = vec![*Trait::ALL.choose(&mut **rng).unwrap()];
+        if rng.gen_bool(0.4) {
+            let second = *Trait::ALL.choose(&mut **rng).unwrap();
+            if !traits.contains(&second) && !traits[0].conflicts_with(second) {
+                traits.push(second);
+            }
+        }
+        Self {
+            name,
+            traits,
+            age,
+            profession: None,
+            level: 1,
+            experience: 0,
+        }
+    }
+
+    /// Experience it takes to reach the next level from the current one.
+    pub fn experience_to_level(&self) -> u32 {
+        self.level * 10
+    }
+
+    /// Adds experience, levelling up as often as it fills up.
+    pub fn gain_experience(&mut self, amount: u32) {
+        if self.level >= Self::MAX_LEVEL {
+            return;
+        }
+        self.experience += amount;
+        while self.level < Self::MAX_LEVEL && self.experience >= self.experience_to_level() {
+            self.experience -= self.experience_to_level();
+            self.level += 1;
+        }
+        if self.level >= Self::MAX_LEVEL {
+            self.experience = 0;
+        }
+    }
+
+    /// Adds what the traits and the levels gained make of the villager in a fight. Nothing of it
+    /// is stored in the stats, so it comes back with the identity when villagers are loaded.
+    pub fn add_to(&self, stats: &mut CardStats) {
+        for villager_trait in &self.traits {
+            villager_trait.add_to(stats);
+        }
+        let levels_gained = self.level - 1;
+        stats.attack_speed += levels_gained as f32 * Self::ATTACK_SPEED_PER_LEVEL;
+        stats.dodge += levels_gained as f32 * Self::DODGE_PER_LEVEL;
+        // a point of damage on every second level
+        stats.damage += (self.level / 2) as usize;
+    }
+
+    /// Extra workers' worth of production from the traits, the level and the profession when
+    /// working the given tile.
+    pub fn work_bonus(&self, tile: &Tile) -> f32 {
+        self.traits.iter().map(Trait::work_bonus).sum::<f32>()
+            + (self.level - 1) as f32 * Self::WORK_BONUS_PER_LEVEL
+            + self
+                .profession
+                .map_or(0.0, |profession| profession.work_bonus(tile))
+    }
+
+    pub fn is_juvenile(&self) -> bool {
+        self.age < Self::ADULT_AGE
+    }
+
+    pub fn is_elder(&self) -> bool {
+        self.age >= Self::ELDER_AGE
+    }
+
+    pub fn stage(&self) -> &'static str {
+        if self.is_juvenile() {
+            "Child"
+        } else if self.is_elder() {
+            "Elder"
+        } else {
+            "Adult"
+        }
+    }
+
+    /// Name, level and profession, with the age and traits on the line below.
+    pub fn title(&self) -> String {
+        let mut title = format!("{}  Lv {}", self.name, self.level);
+        if let Some(profession) = self.profession {
+            title.push_str(&format!("  {}", profession.name()));
+        }
+        let traits: Vec<_> = self.traits.iter().map(Trait::name).collect();
+        format!(
+            "{}\n{} ({})  {}",
+            title,
+            self.stage(),
+            self.age,
+            traits.join(", ")
+        )
+    }
+}
+
+/// A villager as it is written to the save file, where it stood and who it was.
+#[derive(Serialize, Deserialize)]
+pub struct SavedVillager {
+    pub translation: [f32; 2],
+    pub identity: Identity,
+}
+
+/// The identities of all villagers, saved with F5 and loaded back with F9.
+#[derive(Serialize, Deserialize, Default)]
+pub struct VillagerSave {
+    pub villagers: Vec<SavedVillager>,
+}
+
+impl VillagerSave {
+    pub const PATH: &'static str = "villagers.ron";
+    pub const SAVE_KEY: KeyCode = KeyCode::F5;
+    pub const LOAD_KEY: KeyCode = KeyCode::F9;
+
+    /// Pairs every saved villager with the closest of the given villager cards that isn't taken
+    /// yet, by index. Saved villagers left without a card get `None`.
+    pub fn pair(&self, cards: &[Vec2]) -> Vec<Option<usize>> {
+        let mut taken = vec![false; cards.len()];
+        self.villagers
+            .iter()
+            .map(|saved| {
+                let translation = Vec2::from(saved.translation);
+                let closest = cards
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| !taken[*i])
+                    .min_by(|(_, a), (_, b)| {
+                        a.distance(translation).total_cmp(&b.distance(translation))
+                    })
+                    .map(|(i, _)| i);
+                if let Some(i) = closest {
+                    taken[i] = true;
+                }
+                closest
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_villagers(input: Res<ButtonInput<KeyCode>>, cards: Query<(&Card, &Transform)>) {
+    if !input.just_pressed(VillagerSave::SAVE_KEY) {
+        return;
+    }
+    let save = VillagerSave {
+        villagers: cards
+            .iter()
+            .filter_map(|(card, transform)| {
+                Some(SavedVillager {
+                    translation: transform.translation.truncate().to_array(),
+                    identity: card.identity.clone()?,
+                })
+            })
+            .collect(),
+    };
+    let written = ron::ser::to_string_pretty(&save, default())
+        .map_err(|error| error.to_string())
+        .and_then(|value| {
+            std::fs::write(VillagerSave::PATH, value).map_err(|error| error.to_string())
+        });
+    match written {
+        Ok(()) => info!("saved {} villagers", save.villagers.len()),
+        Err(error) => warn!("could not save the villagers: {error}"),
+    }
+}
+
+/// Hands the saved identities back to the villagers standing closest to where they were saved.
+/// Saved villagers left without a card stay gone, so loading neither raises the dead nor houses
+/// more villagers than there is room for.
+#[cfg(not(target_arch = "wasm32"))]
+fn load_villagers(
+    input: Res<ButtonInput<KeyCode>>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut cards: Query<(Entity, &mut Card, &Transform)>,
+) {
+    if !input.just_pressed(VillagerSave::LOAD_KEY) {
+        return;
+    }
+    let save: VillagerSave = match std::fs::read_to_string(VillagerSave::PATH)
+        .map_err(|error| error.to_string())
+        .and_then(|value| ron::from_str(&value).map_err(|error| error.to_string()))
+    {
+        Ok(save) => save,
+        Err(error) => {
+            warn!("could not load the villagers: {error}");
+            return;
+        }
+    };
+    let villagers: Vec<(Entity, Vec2)> = cards
+        .iter()
+        .filter(|(_, card, _)| card.card_type() == CardType::Villager)
+        .map(|(entity, _, transform)| (entity, transform.translation.truncate()))
+        .collect();
+    let translations: Vec<Vec2> = villagers
+        .iter()
+        .map(|(_, translation)| *translation)
+        .collect();
+    let pairs = save.pair(&translations);
+    for (saved, paired) in save.villagers.into_iter().zip(pairs) {
+        let Some(i) = paired else {
+            continue;
+        };
+        let entity = villagers[i].0;
+        if let Ok((_, mut card, _)) = cards.get_mut(entity) {
+            card.identity = Some(saved.identity);
+        }
+        // a child that grew up may breed or build now, and the other way round
+        stack_roots.queue_recomputation(entity);
+    }
+}
+
+/// Marks a villager born from a breed stack, it starts out as a child.
+#[derive(Component)]
+pub struct Newborn;
+
+fn name_villagers(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    mut cards: Query<(Entity, &mut Card, Has<Newborn>), Added<Card>>,
+) {
+    for (entity, mut card, newborn) in &mut cards {
+        if newborn {
+            commands.entity(entity).remove::<Newborn>();
+        }
+        if card.card_type() != CardType::Villager || card.identity.is_some() {
+            continue;
+        }
+        // everyone else arrives grown up
+        let age = if newborn {
+            0
+        } else {
+            Identity::ADULT_AGE + rng.gen_range(0..3)
+        };
+        card.identity = Some(Identity::generate(&mut rng, age));
+    }
+}
+
+/// Villagers grow a day older at the end of every day. Children grow up into workers and elders
+/// may pass away, leaving a grave. A villager held by the player passes away once it is put down.
+#[allow(clippy::too_many_arguments)]
+fn age_villagers(
+    mut commands: Commands,
+    mut end_of_day: EventReader<EndOfDay>,
+    selected: Res<SelectedCard>,
+    mut rng: ResMut<GameRng>,
+    mut stack_roots: ResMut<StackRoots>,
+    mut deaths: EventWriter<CardDied>,
+    mut cards: Query<&mut Card>,
+    transforms: Query<(Entity, &Transform), With<Card>>,
+    mut passing_away: Local<Vec<Entity>>,
+) {
+    for _ in end_of_day.read() {
+        for (entity, _) in &transforms {
+            let Ok(mut card) = cards.get_mut(entity) else {
+                continue;
+            };
+            let Some(identity) = card.identity.as_mut() else {
+                continue;
+            };
+            identity.age += 1;
+            if identity.age == Identity::ADULT_AGE {
+                // a stack the child was holding up may breed or build now
+                stack_roots.queue_recomputation(entity);
+            }
+            if (identity.age >= Identity::MAX_AGE
+                || (identity.is_elder() && rng.gen::<f32>() < Identity::OLD_AGE_DEATH_CHANCE))
+                && !passing_away.contains(&entity)
+            {
+                passing_away.push(entity);
+            }
+        }
+    }
+
+    let mut passed_away = Vec::new();
+    passing_away.retain(|entity| {
+        let held = selected.is_selected(*entity);
+        if !held {
+            passed_away.push(*entity);
+        }
+        held
+    });
+    for entity in passed_away {
+        let Ok((_, transform)) = transforms.get(entity) else {
+            continue;
+        };
+        kill_card(
+            &mut commands,
+            &mut stack_roots,
+            &mut cards,
+            &mut deaths,
+            entity,
+            transform.translation,
+        );
+    }
+}
+
+/// Villagers learn from every blow they land.
+fn train_fighters(mut hits: EventReader<CombatHit>, mut cards: Query<&mut Card>) {
+    for hit in hits.read() {
+        if hit.outcome == AttackOutcome::Dodged {
+            continue;
+        }
+        let Ok(mut card) = cards.get_mut(hit.attacker) else {
+            continue;
+        };
+        let Some(identity) = card.identity.as_mut() else {
+            continue;
+        };
+        let mut experience = Identity::HIT_EXPERIENCE;
+        if hit.killed {
+            experience += Identity::KILL_EXPERIENCE;
+        }
+        identity.gain_experience(experience);
+    }
+}
+
+/// Gives the villagers working a tile experience for the batch they just produced, if it is a
+/// woodcutting tile.
+pub fn reward_workers(tile: &Tile, tile_slots: &TileSlots, cards: &mut Query<&mut Card>) {
+    if !matches!(tile, Tile::Woods { .. } | Tile::LumberCamp { .. }) {
+        return;
+    }
+    for entity in tile_slots.cards() {
+        let Ok(mut card) = cards.get_mut(entity) else {
+            continue;
+        };
+        if let Some(identity) = card.identity.as_mut() {
+            identity.gain_experience(Identity::WOODCUTTING_EXPERIENCE);
+        }
+    }
+}
+
+fn count_skill(mut tiles: Query<(&Tile, &TileSlots, &mut TileBonus)>, cards: Query<&Card>) {
+    for (tile, tile_slots, mut bonus) in &mut tiles {
+        let skill = tile_slots
+            .cards()
+            .filter_map(|entity| cards.get(entity).ok())
+            .filter_map(|card| card.identity.as_ref())
+            .map(|identity| identity.work_bonus(tile))
+            .sum::<f32>();
+        if bonus.skill != skill {
+            bonus.skill = skill;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_survives_a_save_round_trip() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut identity = Identity::generate(&mut rng, Identity::ELDER_AGE);
+        identity.traits = vec![Trait::Strong, Trait::FastWorker];
+        identity.profession = Some(Profession::Lumberjack);
+        identity.level = 3;
+        identity.experience = 7;
+
+        let saved = ron::to_string(&identity).unwrap();
+        let loaded: Identity = ron::from_str(&saved).unwrap();
+        assert_eq!(loaded, identity);
+    }
+
+    #[test]
+    fn loaded_villager_keeps_its_trait_and_level_bonuses() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut identity = Identity::generate(&mut rng, Identity::ADULT_AGE);
+        identity.traits = vec![Trait::Strong];
+        identity.level = 4;
+        let saved = ron::to_string(&identity).unwrap();
+
+        let base = Card::from(CardType::Villager).combat_stats();
+        let loaded = Card {
+            identity: Some(ron::from_str(&saved).unwrap()),
+            ..Card::from(CardType::Villager)
+        };
+        let stats = loaded.combat_stats();
+        // 1 from being strong and 2 from reaching level 2 and 4
+        assert_eq!(stats.damage, base.damage + 3);
+        assert!((stats.attack_speed - (base.attack_speed + 0.3)).abs() < 1e-5);
+        assert!((stats.dodge - (base.dodge + 0.06)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn saved_villagers_return_to_the_closest_cards() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut veteran = Identity::generate(&mut rng, Identity::ELDER_AGE);
+        veteran.traits = vec![Trait::Strong];
+        veteran.level = 3;
+        veteran.experience = 4;
+        let child = Identity::generate(&mut rng, 0);
+        let save = VillagerSave {
+            villagers: vec![
+                SavedVillager {
+                    translation: [2.0, 0.0],
+                    identity: veteran.clone(),
+                },
+                SavedVillager {
+                    translation: [-2.0, 0.0],
+                    identity: child.clone(),
+                },
+            ],
+        };
+        let loaded: VillagerSave =
+            ron::from_str(&ron::ser::to_string_pretty(&save, default()).unwrap()).unwrap();
+
+        // only one card left, close to where the veteran stood
+        let pairs = loaded.pair(&[Vec2::new(1.5, 0.5)]);
+        assert_eq!(pairs, [Some(0), None]);
+
+        let mut villagers = loaded.villagers.into_iter();
+        let restored = Card {
+            identity: Some(villagers.next().unwrap().identity),
+            ..Card::from(CardType::Villager)
+        };
+        assert_eq!(restored.identity.as_ref(), Some(&veteran));
+        let base = Card::from(CardType::Villager).combat_stats();
+        // 1 from being strong and 1 from reaching level 2
+        assert_eq!(restored.combat_stats().damage, base.damage + 2);
+        assert_eq!(villagers.next().unwrap().identity, child);
+    }
+
+    #[test]
+    fn lumberjacks_count_their_axe_once() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut identity = Identity::generate(&mut rng, Identity::ADULT_AGE);
+        identity.traits.clear();
+        identity.profession = Profession::of_tool(CardType::Axe);
+        let mut lumberjack = Card {
+            identity: Some(identity),
+            ..Card::from(CardType::Villager)
+        };
+        lumberjack.equipment.equip(CardType::Axe);
+
+        let woods = Tile::Woods { progress_bar: None };
+        let identity = lumberjack.identity.as_ref().unwrap();
+        let bonus = lumberjack
+            .equipment
+            .production_bonus(&woods, identity.profession)
+            + identity.work_bonus(&woods);
+        assert_eq!(bonus, Profession::Lumberjack.work_bonus(&woods));
+    }
+
+    #[test]
+    fn soldiers_count_their_sword_once() {
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let mut identity = Identity::generate(&mut rng, Identity::ADULT_AGE);
+        identity.traits = vec![Trait::Lazy];
+        let mut soldier = Card {
+            identity: Some(identity),
+            ..Card::from(CardType::Villager)
+        };
+        let base = soldier.combat_stats();
+        soldier.equipment.equip(CardType::Sword);
+        soldier.identity.as_mut().unwrap().profession = Profession::of_tool(CardType::Sword);
+
+        let stats = soldier.combat_stats();
+        assert_eq!(stats.damage, base.damage + 1);
+        assert!((stats.crit_chance - (base.crit_chance + 0.1)).abs() < 1e-5);
+        // with nothing to crit or dodge, every swing lands for the sword damage
+        let defender = CardStats {
+            health: 5,
+            max_health: 5,
+            ..default()
+        };
+        let outcome = CardStats {
+            crit_chance: 0.0,
+            ..stats
+        }
+        .attack(&defender, &mut rng);
+        assert_eq!(
+            outcome,
+            AttackOutcome::Hit {
+                damage: base.damage + 1,
+                critical: false
+            }
+        );
+    }
+}
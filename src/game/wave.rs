@@ -0,0 +1,389 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::game::{
+    card::{Card, CardBundle, CardClass, CardType},
+    day_cycle::{DayCycle, EndOfDay},
+    exploration::FogGrid,
+    label::{hud_text, set_text},
+    progress_bar::ProgressBar,
+    rng::GameRng,
+    tile::Tile,
+};
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DifficultyCurve>()
+            .init_resource::<WaveDirector>()
+            .add_systems(Startup, spawn_wave_announcement)
+            .add_systems(Update, count_days.before(direct_waves))
+            .add_systems(Update, direct_waves)
+            .add_systems(Update, announce_waves.after(direct_waves));
+    }
+}
+
+/// Tunes how waves grow with the threat, which is the number of minutes played plus
+/// [`DifficultyCurve::day_threat`] for every day survived and
+/// [`DifficultyCurve::villager_threat`] for every villager owned.
+#[derive(Resource, Clone)]
+pub struct DifficultyCurve {
+    pub base_interval: f32,
+    pub min_interval: f32,
+    pub interval_per_threat: f32,
+    pub base_group: f32,
+    pub group_per_threat: f32,
+    pub max_group: usize,
+    pub health_per_threat: f32,
+    pub damage_per_threat: f32,
+    pub villager_threat: f32,
+    pub day_threat: f32,
+    /// Enemies joining the goblins once the threat reaches the given level, with their weight.
+    /// Goblins make up the wave while none of them has joined yet.
+    pub roster: Vec<(CardType, f32, u32)>,
+    /// Every this many waves a boss leads the wave.
+    pub boss_every: usize,
+    /// How many seconds ahead the next wave is announced.
+    pub warning_time: f32,
+    /// How much faster the next wave approaches at night.
+    pub night_pace: f32,
+    /// How much bigger waves rolled at night are.
+    pub night_group: f32,
+}
+
+impl Default for DifficultyCurve {
+    fn default() -> Self {
+        Self {
+            base_interval: 40.0,
+            min_interval: 12.0,
+            interval_per_threat: 2.5,
+            base_group: 1.0,
+            group_per_threat: 0.35,
+            max_group: 8,
+            health_per_threat: 0.25,
+            damage_per_threat: 0.12,
+            villager_threat: 0.5,
+            day_threat: 1.0,
+            roster: vec![
+                (CardType::Goblin, 0.0, 6),
+                (CardType::Thief, 1.5, 2),
+                (CardType::Archer, 2.5, 2),
+                (CardType::Brute, 3.5, 1),
+            ],
+            boss_every: 5,
+            warning_time: 10.0,
+            night_pace: 1.5,
+            night_group: 1.5,
+        }
+    }
+}
+
+impl DifficultyCurve {
+    pub fn threat(&self, elapsed: f32, days: u32, villagers: usize) -> f32 {
+        elapsed / 60.0 + days as f32 * self.day_threat + villagers as f32 * self.villager_threat
+    }
+
+    /// Rolls the enemies of the `number`th wave.
+    pub fn wave(&self, threat: f32, number: usize, night: bool, rng: &mut GameRng) -> Wave {
+        let mut group = self.base_group + self.group_per_threat * threat;
+        if night {
+            group *= self.night_group;
+        }
+        let size = (group as usize).clamp(1, self.max_group);
+        let mut wave = self.roll(threat, size, rng);
+        if number.checked_rem(self.boss_every) == Some(0) {
+            wave.enemies.insert(0, CardType::Warlord);
+        }
+        wave
+    }
+
+    /// Rolls a lone enemy as strong as the ones in the waves, for camps calling in defenders.
+    pub fn defender(&self, threat: f32, rng: &mut GameRng) -> Card {
+        let wave = self.roll(threat, 1, rng);
+        wave.enemy(wave.enemies[0])
+    }
+
+    fn roll(&self, threat: f32, size: usize, rng: &mut GameRng) -> Wave {
+        let roster: Vec<(CardType, u32)> = self
+            .roster
+            .iter()
+            .filter(|(_, min_threat, _)| threat >= *min_threat)
+            .map(|(card_type, _, weight)| (*card_type, *weight))
+            .collect();
+        let enemies: Vec<CardType> = (0..size)
+            .map(|_| rng.weighted(&roster).unwrap_or(CardType::Goblin))
+            .collect();
+        Wave {
+            interval: (self.base_interval - self.interval_per_threat * threat)
+                .max(self.min_interval),
+            enemies,
+            health_bonus: (self.health_per_threat * threat) as usize,
+            damage_bonus: (self.damage_per_threat * threat) as usize,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Wave {
+    pub interval: f32,
+    pub enemies: Vec<CardType>,
+    pub health_bonus: usize,
+    pub damage_bonus: usize,
+}
+
+impl Wave {
+    pub fn enemy(&self, card_type: CardType) -> Card {
+        let mut card = Card::from(card_type);
+        card.info.stats.health += self.health_bonus as isize;
+        card.info.stats.max_health += self.health_bonus;
+        card.info.stats.damage += self.damage_bonus;
+        card
+    }
+
+    /// Lists the enemies as "2 Goblin, 1 Thief".
+    pub fn describe(&self) -> String {
+        let mut counts: Vec<(CardType, usize)> = Vec::new();
+        for card_type in &self.enemies {
+            match counts.iter_mut().find(|(counted, _)| counted == card_type) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*card_type, 1)),
+            }
+        }
+        counts
+            .iter()
+            .map(|(card_type, count)| format!("{count} {card_type:?}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Schedules goblin waves. The next wave is rolled from the [`DifficultyCurve`] as soon as the
+/// previous one arrives, so it can be announced before it spawns.
+#[derive(Resource, Default)]
+pub struct WaveDirector {
+    pub elapsed: f32,
+    /// How many days have ended since the game started, every one makes the waves harder.
+    pub days: u32,
+    /// How many waves have arrived so far.
+    pub number: usize,
+    pub next: Option<Wave>,
+    pub countdown: f32,
+    pub since_arrival: Option<f32>,
+}
+
+/// The waves grow with every day the village lives through.
+fn count_days(mut end_of_day: EventReader<EndOfDay>, mut director: ResMut<WaveDirector>) {
+    for _ in end_of_day.read() {
+        director.days += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn direct_waves(
+    mut commands: Commands,
+    time: Res<Time>,
+    cycle: Res<DayCycle>,
+    curve: Res<DifficultyCurve>,
+    fog_grid: Res<FogGrid>,
+    mut director: ResMut<WaveDirector>,
+    mut rng: ResMut<GameRng>,
+    cards: Query<&Card>,
+    tiles: Query<(&Tile, &Transform)>,
+    mut progress_bars: Query<&mut ProgressBar>,
+) {
+    let delta = time.delta_seconds();
+    director.elapsed += delta;
+    if let Some(since_arrival) = &mut director.since_arrival {
+        *since_arrival += delta;
+    }
+
+    if director.next.is_none() {
+        let villagers = cards
+            .iter()
+            .filter(|card| card.class() == CardClass::Villager)
+            .count();
+        let threat = curve.threat(director.elapsed, director.days, villagers);
+        let wave = curve.wave(threat, director.number + 1, cycle.is_night(), &mut rng);
+        director.countdown = wave.interval;
+        director.next = Some(wave);
+    }
+    let Some(wave) = director.next.clone() else {
+        return;
+    };
+    director.countdown -= if cycle.is_night() {
+        delta * curve.night_pace
+    } else {
+        delta
+    };
+
+    // camp progress bars count down to the next wave
+    let mut camps = Vec::new();
+    for (tile, transform) in &tiles {
+        if let Tile::Enemies { progress_bar } = tile {
+            camps.push(transform.translation);
+            if let Some(Ok(mut bar)) = progress_bar.map(|bar| progress_bars.get_mut(bar)) {
+                bar.total = wave.interval;
+                bar.current = (wave.interval - director.countdown).clamp(0.0, wave.interval);
+            }
+        }
+    }
+
+    if director.countdown > 0.0 {
+        return;
+    }
+
+    // without camps left, waves come out of the unexplored wilds
+    if camps.is_empty() {
+        let mut wilds: Vec<IVec2> = fog_grid.keys().copied().collect();
+        wilds.sort_by_key(|location| (location.x, location.y));
+        if !wilds.is_empty() {
+            let location = wilds[rng.gen_range(0..wilds.len())];
+            camps.push(Tile::grid_to_translation(location));
+        }
+    }
+
+    for (i, card_type) in wave.enemies.iter().enumerate() {
+        let Some(origin) = camps.get(i % camps.len().max(1)) else {
+            break;
+        };
+        let offset = (i / camps.len()) as f32 * 0.4;
+        commands.spawn(CardBundle {
+            card: wave.enemy(*card_type),
+            transform: Transform::from_xyz(origin.x + offset, origin.y - offset, 0.0),
+            ..default()
+        });
+    }
+    director.number += 1;
+    director.next = None;
+    director.since_arrival = Some(0.0);
+}
+
+#[derive(Component)]
+pub struct WaveAnnouncement;
+
+fn spawn_wave_announcement(mut commands: Commands) {
+    commands
+        .spawn(
+            hud_text(
+                22.0,
+                Color::rgb(1.0, 0.6, 0.6),
+                Style {
+                    top: Val::Px(10.0),
+                    width: Val::Percent(100.0),
+                    ..default()
+                },
+            )
+            .with_text_justify(JustifyText::Center),
+        )
+        .insert(WaveAnnouncement);
+}
+
+fn announce_waves(
+    curve: Res<DifficultyCurve>,
+    director: Res<WaveDirector>,
+    mut announcements: Query<&mut Text, With<WaveAnnouncement>>,
+) {
+    let value = match (&director.next, director.since_arrival) {
+        (Some(wave), _) if director.countdown <= curve.warning_time => {
+            let mut value = format!(
+                "Wave {} in {:.0}s: {}",
+                director.number + 1,
+                director.countdown.ceil(),
+                wave.describe()
+            );
+            if wave.health_bonus > 0 {
+                value.push_str(&format!(", +{} health", wave.health_bonus));
+            }
+            value
+        }
+        (_, Some(since_arrival)) if since_arrival < 3.0 => {
+            format!("Wave {} has arrived!", director.number)
+        }
+        _ => String::new(),
+    };
+    for mut text in &mut announcements {
+        set_text(&mut text, &value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_waves_are_a_lone_goblin() {
+        let curve = DifficultyCurve::default();
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let wave = curve.wave(0.0, 1, false, &mut rng);
+        assert_eq!(wave.enemies, [CardType::Goblin]);
+        assert_eq!(wave.interval, curve.base_interval);
+        assert_eq!((wave.health_bonus, wave.damage_bonus), (0, 0));
+    }
+
+    #[test]
+    fn goblins_fill_in_while_nobody_on_the_roster_has_joined() {
+        let curve = DifficultyCurve {
+            roster: vec![(CardType::Thief, 1.0, 1), (CardType::Brute, 2.0, 1)],
+            ..default()
+        };
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let wave = curve.wave(0.0, 1, false, &mut rng);
+        assert_eq!(wave.enemies, [CardType::Goblin]);
+    }
+
+    #[test]
+    fn every_day_survived_adds_to_the_threat() {
+        let curve = DifficultyCurve::default();
+        assert_eq!(curve.threat(0.0, 0, 0), 0.0);
+        assert_eq!(curve.threat(0.0, 3, 0), 3.0 * curve.day_threat);
+        assert!(curve.threat(120.0, 2, 1) > curve.threat(120.0, 1, 1));
+    }
+
+    #[test]
+    fn bosses_lead_every_few_waves() {
+        let curve = DifficultyCurve::default();
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        for number in 1..=2 * curve.boss_every {
+            let wave = curve.wave(0.0, number, false, &mut rng);
+            let led_by_boss = wave.enemies.first() == Some(&CardType::Warlord);
+            assert_eq!(
+                led_by_boss,
+                number.checked_rem(curve.boss_every) == Some(0),
+                "wave {number}"
+            );
+        }
+    }
+
+    #[test]
+    fn waves_grow_with_the_threat() {
+        let curve = DifficultyCurve::default();
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let early = curve.wave(0.0, 1, false, &mut rng);
+        let late = curve.wave(10.0, 1, false, &mut rng);
+        assert!(late.enemies.len() > early.enemies.len());
+        assert!(late.interval < early.interval);
+        assert!(late.health_bonus > early.health_bonus);
+        assert!(late.damage_bonus > early.damage_bonus);
+        assert!(late.interval >= curve.min_interval);
+
+        let endgame = curve.wave(1000.0, 1, true, &mut rng);
+        assert_eq!(endgame.enemies.len(), curve.max_group);
+        assert_eq!(endgame.interval, curve.min_interval);
+    }
+
+    #[test]
+    fn defenders_are_scaled_like_waves() {
+        let curve = DifficultyCurve::default();
+        let mut rng = GameRng::from_seed(GameRng::DEFAULT_SEED);
+        let wave = curve.wave(10.0, 1, false, &mut rng);
+        for _ in 0..curve.boss_every {
+            let defender = curve.defender(10.0, &mut rng);
+            assert_ne!(defender.card_type(), CardType::Warlord);
+            let base = Card::from(defender.card_type()).info.stats;
+            let stats = &defender.info.stats;
+            assert_eq!(stats.max_health, base.max_health + wave.health_bonus);
+            assert_eq!(stats.damage, base.damage + wave.damage_bonus);
+        }
+    }
+}